@@ -0,0 +1,351 @@
+use std::path::PathBuf;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::error::{AppError, AppErrorKind, WithWarnings};
+use crate::templates::{edit_project_instances_in, instance_count, InstanceContext, InstanceEditResult};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateMode {
+    /// Neither a template nor a replica.
+    None,
+    Template,
+    Replica,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct VariableFilter {
+    name: String,
+    value: Value,
+}
+
+/// Layout coordinates, an instance matches when its position is inside.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct BoundingBox {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// Every field left out matches everything.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct InstanceFilter {
+    #[serde(default)]
+    object_type: Option<String>,
+    #[serde(default)]
+    layer_name: Option<String>,
+    /// `*` and `?` wildcards, case insensitive.
+    #[serde(default)]
+    layout_name: Option<String>,
+    #[serde(default)]
+    template_mode: Option<TemplateMode>,
+    #[serde(default)]
+    instance_variable: Option<VariableFilter>,
+    #[serde(default)]
+    bounding_box: Option<BoundingBox>,
+}
+
+/// Values use the units of Construct's properties bar: degrees for the angle, 0-100 for the opacity.
+/// Variables and properties the instance doesn't have are left alone rather than added, with a warning.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "property", rename_all = "camelCase")]
+pub enum PropertyAssignment {
+    X { value: f64 },
+    Y { value: f64 },
+    Width { value: f64 },
+    Height { value: f64 },
+    Angle { value: f64 },
+    Opacity { value: f64 },
+    InstanceVariable { name: String, value: Value },
+    BehaviorProperty { behavior: String, name: String, value: Value },
+    /// A property of the object's plugin, such as a Sprite's initial animation.
+    PluginProperty { name: String, value: Value },
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BulkEditPayload {
+    project_path: String,
+    filter: InstanceFilter,
+    assignments: Vec<PropertyAssignment>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    // Classic two-pointer match, backtracking to the last `*`.
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Numbers are compared by value so `5` matches `5.0`.
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn world_number(instance: &Map<String, Value>, key: &str) -> Option<f64> {
+    instance.get("world").and_then(|w| w.get(key)).and_then(|v| v.as_f64())
+}
+
+impl InstanceFilter {
+    fn matches(&self, context: &InstanceContext, instance: &Map<String, Value>) -> bool {
+        if let Some(object_type) = &self.object_type {
+            if instance.get("type").and_then(|t| t.as_str()) != Some(object_type.as_str()) {
+                return false;
+            }
+        }
+        if self.layer_name.as_ref().is_some_and(|layer_name| layer_name != context.layer_name) {
+            return false;
+        }
+        if self.layout_name.as_ref().is_some_and(|pattern| !glob_match(pattern, context.layout_name)) {
+            return false;
+        }
+        if let Some(template_mode) = self.template_mode {
+            let mode = instance.get("template").and_then(|t| t.get("mode")).and_then(|m| m.as_str());
+            let actual = match mode {
+                Some("template") => TemplateMode::Template,
+                Some("replica") => TemplateMode::Replica,
+                _ => TemplateMode::None,
+            };
+            if actual != template_mode {
+                return false;
+            }
+        }
+        if let Some(variable) = &self.instance_variable {
+            let value = instance.get("instanceVariables").and_then(|v| v.get(&variable.name));
+            if !value.is_some_and(|value| values_equal(value, &variable.value)) {
+                return false;
+            }
+        }
+        if let Some(bbox) = self.bounding_box {
+            let (Some(x), Some(y)) = (world_number(instance, "x"), world_number(instance, "y")) else { return false };
+            if x < bbox.x || y < bbox.y || x > bbox.x + bbox.width || y > bbox.y + bbox.height {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Replaces an existing key of `map`, a missing key stays missing. Returns whether the key was there.
+fn replace_existing(map: Option<&mut Map<String, Value>>, key: &str, value: Value) -> bool {
+    match map.and_then(|m| m.get_mut(key)) {
+        Some(slot) => {
+            *slot = value;
+            true
+        }
+        None => false,
+    }
+}
+
+fn as_object_mut(value: Option<&mut Value>) -> Option<&mut Map<String, Value>> {
+    value.and_then(|v| v.as_object_mut())
+}
+
+impl PropertyAssignment {
+    /// Returns false when the instance doesn't have the property, it is then left as is.
+    fn apply(&self, instance: &mut Map<String, Value>) -> bool {
+        match self {
+            PropertyAssignment::X { value } => set_world(instance, "x", *value),
+            PropertyAssignment::Y { value } => set_world(instance, "y", *value),
+            PropertyAssignment::Width { value } => set_world(instance, "width", *value),
+            PropertyAssignment::Height { value } => set_world(instance, "height", *value),
+            PropertyAssignment::Angle { value } => set_world(instance, "angle", value.to_radians()),
+            PropertyAssignment::Opacity { value } => {
+                // Opacity is the alpha of the world color.
+                let alpha = as_object_mut(instance.get_mut("world"))
+                    .and_then(|world| world.get_mut("color"))
+                    .and_then(|color| color.get_mut(3));
+                match alpha {
+                    Some(alpha) => {
+                        *alpha = Value::from(value.clamp(0.0, 100.0) / 100.0);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            PropertyAssignment::InstanceVariable { name, value } => {
+                replace_existing(as_object_mut(instance.get_mut("instanceVariables")), name, value.clone())
+            }
+            PropertyAssignment::BehaviorProperty { behavior, name, value } => {
+                let properties = as_object_mut(instance.get_mut("behaviors"))
+                    .and_then(|behaviors| behaviors.get_mut(behavior))
+                    .and_then(|b| b.get_mut("properties"))
+                    .and_then(|p| p.as_object_mut());
+                replace_existing(properties, name, value.clone())
+            }
+            PropertyAssignment::PluginProperty { name, value } => {
+                replace_existing(as_object_mut(instance.get_mut("properties")), name, value.clone())
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            PropertyAssignment::X { .. } => "X".to_string(),
+            PropertyAssignment::Y { .. } => "Y".to_string(),
+            PropertyAssignment::Width { .. } => "width".to_string(),
+            PropertyAssignment::Height { .. } => "height".to_string(),
+            PropertyAssignment::Angle { .. } => "angle".to_string(),
+            PropertyAssignment::Opacity { .. } => "opacity".to_string(),
+            PropertyAssignment::InstanceVariable { name, .. } => format!("instance variable '{}'", name),
+            PropertyAssignment::BehaviorProperty { behavior, name, .. } => format!("'{}' property '{}'", behavior, name),
+            PropertyAssignment::PluginProperty { name, .. } => format!("property '{}'", name),
+        }
+    }
+}
+
+fn set_world(instance: &mut Map<String, Value>, key: &str, value: f64) -> bool {
+    replace_existing(as_object_mut(instance.get_mut("world")), key, Value::from(value))
+}
+
+/// Applies `assignments` to every instance matching `filter`, across all layouts, in one write.
+#[tauri::command]
+pub fn bulk_edit_instances(app: tauri::AppHandle, payload: BulkEditPayload) -> Result<WithWarnings<InstanceEditResult>, AppError> {
+    if payload.assignments.is_empty() {
+        return Err(AppError::new(AppErrorKind::InvalidInput, "No property to set"));
+    }
+    let project_base_path = PathBuf::from(&payload.project_path);
+    // Per assignment, how many matching instances don't have the property.
+    let mut missing = vec![0; payload.assignments.len()];
+    let edited = edit_project_instances_in(&app, &project_base_path, "bulk_edit_instances", payload.dry_run, |context, instance| {
+        if !payload.filter.matches(context, instance) {
+            return false;
+        }
+        for (assignment, missing) in payload.assignments.iter().zip(missing.iter_mut()) {
+            if !assignment.apply(instance) {
+                *missing += 1;
+            }
+        }
+        true
+    })?;
+    let mut warnings = edited.warnings;
+    for (assignment, missing) in payload.assignments.iter().zip(missing) {
+        if missing > 0 {
+            warnings.push(AppError::new(AppErrorKind::InvalidInput, format!("{} matching instances have no {}, it was not added to them", missing, assignment.describe())));
+        }
+    }
+    let layouts = edited.value;
+    let message = if payload.dry_run {
+        format!("Dry run: {} instances across {} layouts would be changed.", instance_count(&layouts), layouts.len())
+    } else {
+        format!("Changed {} instances across {} layouts.", instance_count(&layouts), layouts.len())
+    };
+    Ok(WithWarnings { value: InstanceEditResult::new(payload.dry_run, message, layouts), warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CONTEXT: InstanceContext = InstanceContext { layout_name: "Level 1", layout_path: "layouts/Level 1.json", layer_name: "Game" };
+
+    fn sprite() -> Map<String, Value> {
+        json!({
+            "type": "Enemy",
+            "uid": 1,
+            "world": { "x": 100, "y": 50, "width": 32, "height": 32, "angle": 0, "color": [1, 1, 1, 1] },
+            "instanceVariables": { "health": 5 },
+            "behaviors": { "Platform": { "properties": { "max-speed": 330 } } },
+            "properties": { "initial-animation": "Idle" },
+            "template": { "mode": "replica", "sourceTemplateName": "grunt" }
+        }).as_object().unwrap().clone()
+    }
+
+    fn filter(filter: Value) -> InstanceFilter {
+        serde_json::from_value(filter).unwrap()
+    }
+
+    fn assignment(assignment: Value) -> PropertyAssignment {
+        serde_json::from_value(assignment).unwrap()
+    }
+
+    #[test]
+    fn filters_match_each_criterion() {
+        let instance = sprite();
+        for (matching, other) in [
+            (json!({}), None),
+            (json!({ "object_type": "Enemy" }), Some(json!({ "object_type": "Player" }))),
+            (json!({ "layer_name": "Game" }), Some(json!({ "layer_name": "HUD" }))),
+            (json!({ "layout_name": "level*" }), Some(json!({ "layout_name": "Level ?0" }))),
+            (json!({ "template_mode": "replica" }), Some(json!({ "template_mode": "none" }))),
+            (json!({ "instance_variable": { "name": "health", "value": 5.0 } }), Some(json!({ "instance_variable": { "name": "health", "value": "5" } }))),
+            (json!({ "bounding_box": { "x": 0, "y": 0, "width": 100, "height": 50 } }), Some(json!({ "bounding_box": { "x": 0, "y": 0, "width": 99, "height": 50 } }))),
+        ] {
+            assert!(filter(matching.clone()).matches(&CONTEXT, &instance), "{}", matching);
+            if let Some(other) = other {
+                assert!(!filter(other.clone()).matches(&CONTEXT, &instance), "{}", other);
+            }
+        }
+        assert!(filter(json!({ "template_mode": "none" })).matches(&CONTEXT, &json!({ "type": "Enemy" }).as_object().unwrap().clone()));
+        assert!(!filter(json!({ "instance_variable": { "name": "ammo", "value": 0 } })).matches(&CONTEXT, &instance));
+    }
+
+    #[test]
+    fn glob_match_handles_wildcards() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("Level ?", "level 2"));
+        assert!(glob_match("*boss*", "Final Boss Room"));
+        assert!(!glob_match("Level ?", "Level 10"));
+        assert!(!glob_match("*room", "Room 1"));
+    }
+
+    #[test]
+    fn assignments_convert_from_properties_bar_units() {
+        let mut instance = sprite();
+        assert!(assignment(json!({ "property": "angle", "value": 90 })).apply(&mut instance));
+        assert_eq!(instance["world"]["angle"], std::f64::consts::FRAC_PI_2);
+        assert!(assignment(json!({ "property": "opacity", "value": 25 })).apply(&mut instance));
+        assert_eq!(instance["world"]["color"][3], 0.25);
+        assert!(assignment(json!({ "property": "opacity", "value": 150 })).apply(&mut instance));
+        assert_eq!(instance["world"]["color"][3], 1.0);
+        assert!(assignment(json!({ "property": "x", "value": 12.5 })).apply(&mut instance));
+        assert_eq!(instance["world"]["x"], 12.5);
+    }
+
+    #[test]
+    fn assignments_to_missing_keys_add_nothing() {
+        let mut instance = sprite();
+        assert!(assignment(json!({ "property": "instanceVariable", "name": "health", "value": 10 })).apply(&mut instance));
+        assert!(assignment(json!({ "property": "behaviorProperty", "behavior": "Platform", "name": "max-speed", "value": 100 })).apply(&mut instance));
+        assert!(assignment(json!({ "property": "pluginProperty", "name": "initial-animation", "value": "Run" })).apply(&mut instance));
+        let before = instance.clone();
+        for missing in [
+            json!({ "property": "instanceVariable", "name": "ammo", "value": 10 }),
+            json!({ "property": "behaviorProperty", "behavior": "Bullet", "name": "speed", "value": 100 }),
+            json!({ "property": "pluginProperty", "name": "text", "value": "Hi" }),
+        ] {
+            assert!(!assignment(missing.clone()).apply(&mut instance), "{}", missing);
+        }
+        assert_eq!(instance, before);
+        assert_eq!(instance["instanceVariables"]["health"], 10);
+        assert_eq!(instance["properties"]["initial-animation"], "Run");
+
+        let mut text = json!({ "type": "Text", "world": { "x": 0 } }).as_object().unwrap().clone();
+        assert!(!assignment(json!({ "property": "opacity", "value": 50 })).apply(&mut text));
+        assert!(text["world"].get("color").is_none());
+    }
+}
@@ -0,0 +1,352 @@
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::ser::{Formatter, PrettyFormatter, Serializer};
+use serde_json::Value;
+
+use crate::error::{AppError, AppErrorKind};
+use crate::journal;
+use crate::snapshot::{app_data_dir, create_snapshot, SnapshotManifest};
+
+/// Writes JSON the way Construct does: tab indentation and numbers as `JSON.stringify` prints them.
+/// Key order is kept as read thanks to serde_json's `preserve_order`.
+struct ConstructFormatter {
+    pretty: PrettyFormatter<'static>,
+}
+
+impl ConstructFormatter {
+    fn new() -> Self {
+        ConstructFormatter { pretty: PrettyFormatter::with_indent(b"\t") }
+    }
+}
+
+/// `100.0` is written `100`, and exponents are only used below 1e-6 and from 1e21 on, with an explicit `+`.
+fn js_number(value: f64) -> String {
+    if !value.is_finite() {
+        return "null".to_string();
+    }
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    let abs = value.abs();
+    if (1e-6..1e21).contains(&abs) {
+        return format!("{}", value);
+    }
+    let formatted = format!("{:e}", value);
+    match formatted.split_once('e') {
+        Some((mantissa, exponent)) if !exponent.starts_with('-') => format!("{}e+{}", mantissa, exponent),
+        _ => formatted,
+    }
+}
+
+impl Formatter for ConstructFormatter {
+    fn write_f32<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f32) -> io::Result<()> {
+        writer.write_all(js_number(value as f64).as_bytes())
+    }
+
+    fn write_f64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+        writer.write_all(js_number(value).as_bytes())
+    }
+
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.begin_array(writer)
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_array(writer)
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(&mut self, writer: &mut W, first: bool) -> io::Result<()> {
+        self.pretty.begin_array_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_array_value(writer)
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(&mut self, writer: &mut W, first: bool) -> io::Result<()> {
+        self.pretty.begin_object_key(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_object_value(writer)
+    }
+}
+
+pub fn serialize_json(path: &Path, data: &Value) -> Result<String, AppError> {
+    let mut buffer = Vec::new();
+    let mut serializer = Serializer::with_formatter(&mut buffer, ConstructFormatter::new());
+    data.serialize(&mut serializer).map_err(|e| AppError::json(path, e))?;
+    String::from_utf8(buffer).map_err(|e| AppError::internal(e.to_string()).with_file(path))
+}
+
+/// Keeps the line endings and trailing newline of the file being replaced, so git only sees real edits.
+fn match_file_layout(original: &str, mut content: String) -> String {
+    let newline = if original.contains("\r\n") { "\r\n" } else { "\n" };
+    if newline != "\n" {
+        content = content.replace('\n', newline);
+    }
+    if original.ends_with('\n') && !content.ends_with('\n') {
+        content.push_str(newline);
+    }
+    content
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonChangeOp {
+    Add,
+    Remove,
+    Replace,
+}
+
+/// One difference between two JSON values, `path` being a JSON pointer relative to the compared value.
+#[derive(Serialize, Debug, Clone)]
+pub struct JsonChange {
+    op: JsonChangeOp,
+    path: String,
+    old_value: Option<Value>,
+    new_value: Option<Value>,
+}
+
+fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Object keys are compared one by one, anything else (arrays included) is replaced as a whole.
+pub fn json_diff(before: Option<&Value>, after: Option<&Value>) -> Vec<JsonChange> {
+    let mut changes = Vec::new();
+    diff_into(before, after, String::new(), &mut changes);
+    changes
+}
+
+fn diff_into(before: Option<&Value>, after: Option<&Value>, path: String, changes: &mut Vec<JsonChange>) {
+    match (before, after) {
+        (None, None) => {}
+        (None, Some(new)) => changes.push(JsonChange { op: JsonChangeOp::Add, path, old_value: None, new_value: Some(new.clone()) }),
+        (Some(old), None) => changes.push(JsonChange { op: JsonChangeOp::Remove, path, old_value: Some(old.clone()), new_value: None }),
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            for (key, old_value) in old {
+                diff_into(Some(old_value), new.get(key), format!("{}/{}", path, escape_pointer_token(key)), changes);
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    diff_into(None, Some(new_value), format!("{}/{}", path, escape_pointer_token(key)), changes);
+                }
+            }
+        }
+        (Some(old), Some(new)) => {
+            if old != new {
+                changes.push(JsonChange { op: JsonChangeOp::Replace, path, old_value: Some(old.clone()), new_value: Some(new.clone()) });
+            }
+        }
+    }
+}
+
+/// Every file a mutating command is about to write. Nothing touches the disk until `commit`,
+/// which snapshots the current content of those files and records the edit in the undo journal first.
+pub struct ChangeSet {
+    project_dir: PathBuf,
+    command: String,
+    writes: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl ChangeSet {
+    pub fn new(project_dir: &Path, command: &str) -> Self {
+        ChangeSet { project_dir: project_dir.to_path_buf(), command: command.to_string(), writes: Vec::new() }
+    }
+
+    /// Files that would be written back as they are are left out, they'd only add an undo step that does nothing.
+    pub fn write_json(&mut self, path: PathBuf, data: &Value) -> Result<(), AppError> {
+        let mut content = serialize_json(&path, data)?;
+        if let Ok(original) = fs::read_to_string(&path) {
+            content = match_file_layout(&original, content);
+            if content == original {
+                return Ok(());
+            }
+        }
+        self.writes.push((path, content.into_bytes()));
+        Ok(())
+    }
+
+    /// Binary files such as frame images, written as given unless the file already holds those bytes.
+    pub fn write_bytes(&mut self, path: PathBuf, content: Vec<u8>) {
+        if fs::read(&path).is_ok_and(|original| original == content) {
+            return;
+        }
+        self.writes.push((path, content));
+    }
+
+    pub fn commit(self, data_dir: &Path) -> Result<Option<SnapshotManifest>, AppError> {
+        if self.writes.is_empty() {
+            return Ok(None);
+        }
+        let paths: Vec<PathBuf> = self.writes.iter().map(|(path, _)| path.clone()).collect();
+        let snapshot = create_snapshot(data_dir, &self.project_dir, &self.command, &paths)?;
+        journal::record(data_dir, &self.project_dir, &self.command, &self.writes)?;
+        for (path, content) in self.writes {
+            fs::write(&path, content).map_err(|e| AppError::io(&path, e))?;
+        }
+        Ok(Some(snapshot))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JsonFileWrite {
+    /// Absolute path, it must be inside the project folder.
+    path: String,
+    data: Value,
+}
+
+/// `path` if it is inside `project_dir`. `..` is refused outright, `strip_prefix` alone compares
+/// components without resolving them and would let `<project>/../elsewhere.json` through.
+/// Both are also compared once resolved, so a symlink can't lead out of the project either.
+fn project_file_path(project_dir: &Path, path: &str) -> Result<PathBuf, AppError> {
+    let path = PathBuf::from(path);
+    let outside = || AppError::new(AppErrorKind::InvalidInput, "File is outside of the project folder").with_file(&path);
+    if path.components().any(|c| c == Component::ParentDir) || !path.starts_with(project_dir) || path == project_dir {
+        return Err(outside());
+    }
+    let canonical_dir = fs::canonicalize(project_dir).map_err(|e| AppError::io(project_dir, e))?;
+    // New files don't exist yet, their folder does.
+    let canonical_path = match fs::canonicalize(&path) {
+        Ok(canonical) => canonical,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => fs::canonicalize(parent).map_err(|e| AppError::io(parent, e))?.join(name),
+            _ => return Err(outside()),
+        },
+        Err(e) => return Err(AppError::io(&path, e)),
+    };
+    if !canonical_path.starts_with(&canonical_dir) || canonical_path == canonical_dir {
+        return Err(outside());
+    }
+    Ok(path)
+}
+
+/// Lets the frontend write object type, family and layout JSON through the same path as the backend commands.
+#[tauri::command]
+pub fn write_project_json(app: tauri::AppHandle, project_path: String, command: String, files: Vec<JsonFileWrite>) -> Result<(), AppError> {
+    let project_dir = Path::new(&project_path);
+    if project_dir.components().any(|c| c == Component::ParentDir) {
+        return Err(AppError::new(AppErrorKind::InvalidInput, "The project path can't contain '..'").with_file(project_dir));
+    }
+    // The path comes from the frontend, it must at least be a Construct project.
+    if !project_dir.join("project.c3proj").is_file() {
+        return Err(AppError::new(AppErrorKind::InvalidInput, "Not a Construct project folder, it has no project.c3proj").with_file(project_dir));
+    }
+    let mut changes = ChangeSet::new(project_dir, &command);
+    for file in files {
+        changes.write_json(project_file_path(project_dir, &file.path)?, &file.data)?;
+    }
+    changes.commit(&app_data_dir(&app)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn js_number_prints_like_json_stringify() {
+        for (value, expected) in [
+            (100.0, "100"),
+            (-3.0, "-3"),
+            (1.5, "1.5"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (-0.0, "0"),
+            (0.000001, "0.000001"),
+            (1.5e-7, "1.5e-7"),
+            (-2e-9, "-2e-9"),
+            (123e18, "123000000000000000000"),
+            (1e21, "1e+21"),
+            (-2.5e25, "-2.5e+25"),
+            (f64::NAN, "null"),
+            (f64::INFINITY, "null"),
+        ] {
+            assert_eq!(js_number(value), expected, "{:e}", value);
+        }
+    }
+
+    #[test]
+    fn serialize_json_matches_construct_output() {
+        // What `JSON.stringify(value, null, "\t")` gives, which is how Construct saves project files.
+        let data = json!({
+            "name": "Layout 1",
+            "width": 1708.0,
+            "layers": [{ "instances": [], "uid": 0, "opacity": 0.5 }],
+            "scale": 1,
+            "flags": [true, null],
+            "empty": {}
+        });
+        let expected = "{\n\t\"name\": \"Layout 1\",\n\t\"width\": 1708,\n\t\"layers\": [\n\t\t{\n\t\t\t\"instances\": [],\n\t\t\t\"uid\": 0,\n\t\t\t\"opacity\": 0.5\n\t\t}\n\t],\n\t\"scale\": 1,\n\t\"flags\": [\n\t\ttrue,\n\t\tnull\n\t],\n\t\"empty\": {}\n}";
+        assert_eq!(serialize_json(Path::new("test.json"), &data).unwrap(), expected);
+    }
+
+    #[test]
+    fn serialize_json_keeps_full_precision_numbers() {
+        // Instance positions as Construct saves them, parsing must not be off by one unit in the last place.
+        let original = "{\n\t\"x\": 229.81246145434534,\n\t\"y\": -1017.4880303030303,\n\t\"angle\": 0.7853981633974483,\n\t\"opacity\": 0.30000000000000004,\n\t\"width\": 5e-324\n}";
+        let data: Value = serde_json::from_str(original).unwrap();
+        assert_eq!(serialize_json(Path::new("Main.json"), &data).unwrap(), original);
+    }
+
+    #[test]
+    fn write_json_skips_unchanged_files() {
+        let dir = std::env::temp_dir().join(format!("construct-crawler-test-changes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Main.json");
+        // Construct's layout: CRLF line endings and a trailing newline, both kept on rewrite.
+        fs::write(&path, "{\r\n\t\"uid\": 1,\r\n\t\"width\": 100\r\n}\r\n").unwrap();
+        let mut changes = ChangeSet::new(&dir, "test");
+        changes.write_json(path.clone(), &json!({ "uid": 1, "width": 100.0 })).unwrap();
+        assert!(changes.writes.is_empty());
+        changes.write_json(path.clone(), &json!({ "uid": 1, "width": 200 })).unwrap();
+        assert_eq!(changes.writes, vec![(path, b"{\r\n\t\"uid\": 1,\r\n\t\"width\": 200\r\n}\r\n".to_vec())]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn project_file_path_rejects_paths_leaving_the_project() {
+        let root = std::env::temp_dir().join(format!("construct-crawler-test-project-path-{}", std::process::id()));
+        let project = root.join("game");
+        fs::create_dir_all(project.join("layouts")).unwrap();
+        fs::create_dir_all(root.join("other")).unwrap();
+        let inside = project.join("layouts/Main.json");
+        fs::write(&inside, "{}").unwrap();
+        assert_eq!(project_file_path(&project, inside.to_str().unwrap()).unwrap(), inside);
+        // Not written yet, but in an existing project folder.
+        let new_file = project.join("layouts/New.json");
+        assert_eq!(project_file_path(&project, new_file.to_str().unwrap()).unwrap(), new_file);
+
+        let mut outside = vec![
+            project.join("../elsewhere.json"),
+            project.join("layouts/../../elsewhere.json"),
+            root.join("other/a.json"),
+            project.clone(),
+        ];
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.join("other"), project.join("linked")).unwrap();
+            outside.push(project.join("linked/a.json"));
+        }
+        for path in outside {
+            let error = project_file_path(&project, path.to_str().unwrap()).unwrap_err();
+            assert_eq!(error.kind, AppErrorKind::InvalidInput, "{}", path.display());
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
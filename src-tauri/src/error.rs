@@ -0,0 +1,130 @@
+use std::fmt;
+use std::path::Path;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AppErrorKind {
+    Io,
+    NotFound,
+    Zip,
+    Json,
+    Xml,
+    /// A PNG that can't be decoded or encoded.
+    Image,
+    /// The file parsed but doesn't have the shape Construct writes.
+    InvalidStructure,
+    /// The request doesn't fit the project (unknown template, wrong object type, name collision...).
+    InvalidInput,
+    /// A file no longer has the content the app expects, usually because it was edited elsewhere.
+    Conflict,
+    Internal,
+}
+
+/// Error (or warning) sent to the frontend, pointing at the file and location it is about.
+#[derive(Serialize, Debug, Clone)]
+pub struct AppError {
+    pub kind: AppErrorKind,
+    pub file: Option<String>,
+    pub json_pointer: Option<String>,
+    pub xml_position: Option<u64>,
+    pub message: String,
+}
+
+impl AppError {
+    pub fn new(kind: AppErrorKind, message: impl Into<String>) -> Self {
+        AppError { kind, file: None, json_pointer: None, xml_position: None, message: message.into() }
+    }
+
+    pub fn with_file(mut self, file: impl AsRef<Path>) -> Self {
+        self.file = Some(file.as_ref().to_string_lossy().into_owned());
+        self
+    }
+
+    pub fn at_pointer(mut self, pointer: impl Into<String>) -> Self {
+        self.json_pointer = Some(pointer.into());
+        self
+    }
+
+    pub fn at_xml_position(mut self, position: u64) -> Self {
+        self.xml_position = Some(position);
+        self
+    }
+
+    pub fn not_found(file: impl AsRef<Path>) -> Self {
+        AppError::new(AppErrorKind::NotFound, "File not found").with_file(file)
+    }
+
+    pub fn io(file: impl AsRef<Path>, e: std::io::Error) -> Self {
+        let kind = if e.kind() == std::io::ErrorKind::NotFound { AppErrorKind::NotFound } else { AppErrorKind::Io };
+        AppError::new(kind, e.to_string()).with_file(file)
+    }
+
+    pub fn json(file: impl AsRef<Path>, e: serde_json::Error) -> Self {
+        AppError::new(AppErrorKind::Json, e.to_string()).with_file(file)
+    }
+
+    pub fn image(file: impl AsRef<Path>, e: impl fmt::Display) -> Self {
+        AppError::new(AppErrorKind::Image, e.to_string()).with_file(file)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        AppError::new(AppErrorKind::Internal, message)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(file) = &self.file {
+            write!(f, " ({}", file)?;
+            if let Some(pointer) = &self.json_pointer {
+                write!(f, " at {}", pointer)?;
+            }
+            if let Some(position) = self.xml_position {
+                write!(f, " at byte {}", position)?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+// The archive code reads and writes many files in a row with `?`, the command adds the file it was about.
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        let kind = if e.kind() == std::io::ErrorKind::NotFound { AppErrorKind::NotFound } else { AppErrorKind::Io };
+        AppError::new(kind, e.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for AppError {
+    fn from(e: zip::result::ZipError) -> Self {
+        AppError::new(AppErrorKind::Zip, e.to_string())
+    }
+}
+
+impl From<walkdir::Error> for AppError {
+    fn from(e: walkdir::Error) -> Self {
+        let error = AppError::new(AppErrorKind::Io, e.to_string());
+        match e.path() {
+            Some(path) => error.with_file(path),
+            None => error,
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for AppError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        AppError::internal(e.to_string())
+    }
+}
+
+/// A command result along with everything that was skipped on the way.
+#[derive(Serialize, Debug, Clone)]
+pub struct WithWarnings<T> {
+    pub value: T,
+    pub warnings: Vec<AppError>,
+}
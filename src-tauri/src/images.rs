@@ -0,0 +1,1206 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::task::JoinSet;
+
+use crate::changes::ChangeSet;
+use crate::error::{AppError, AppErrorKind, WithWarnings};
+use crate::indexer::frame_image_name;
+use crate::polygon::{trace_polygon, Point, PolygonShape};
+use crate::project::Project;
+use crate::{read_json_file, snapshot, Payload};
+
+/// Larger than any texture Construct can load, a bigger size is most likely a typo.
+const MAX_IMAGE_SIZE: u32 = 16384;
+
+/// The gaussian kernel grows with the radius: past this a blur takes minutes on a large frame
+/// and leaves little more than its average color.
+const MAX_GAUSSIAN_RADIUS: u32 = 250;
+
+/// 8 bit RGBA pixels, not premultiplied, as PNG stores them.
+#[derive(Debug, Clone)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Fully transparent.
+    pub fn new(width: u32, height: u32) -> Self {
+        RgbaImage { width, height, pixels: vec![0; width as usize * height as usize * 4] }
+    }
+
+    pub fn read_png(path: &Path) -> Result<RgbaImage, AppError> {
+        let file = File::open(path).map_err(|e| AppError::io(path, e))?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        // Palettes, transparency chunks, low and high bit depths all come out as 8 bit channels.
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info().map_err(|e| AppError::image(path, e))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(|e| AppError::image(path, e))?;
+        let data = &buffer[..info.buffer_size()];
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => data.to_vec(),
+            png::ColorType::Rgb => data.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => data.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale => data.iter().flat_map(|g| [*g, *g, *g, 255]).collect(),
+            png::ColorType::Indexed => return Err(AppError::image(path, "Palette was not expanded")),
+        };
+        Ok(RgbaImage { width: info.width, height: info.height, pixels })
+    }
+
+    /// `path` is only used in errors.
+    pub fn encode_png(&self, path: &Path) -> Result<Vec<u8>, AppError> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| AppError::image(path, e))?;
+        writer.write_image_data(&self.pixels).map_err(|e| AppError::image(path, e))?;
+        writer.finish().map_err(|e| AppError::image(path, e))?;
+        Ok(bytes)
+    }
+
+    /// Copies the image onto a transparent `width` x `height` canvas with its top left corner at `x`, `y`,
+    /// cropping whatever falls outside.
+    pub fn place(&self, width: u32, height: u32, x: i64, y: i64) -> RgbaImage {
+        let mut canvas = RgbaImage::new(width, height);
+        let first_column = x.max(0);
+        let last_column = (x + self.width as i64).min(width as i64);
+        if first_column >= last_column {
+            return canvas;
+        }
+        let row_bytes = (last_column - first_column) as usize * 4;
+        for target_y in y.max(0)..(y + self.height as i64).min(height as i64) {
+            let source_start = (((target_y - y) * self.width as i64 + (first_column - x)) * 4) as usize;
+            let target_start = ((target_y * width as i64 + first_column) * 4) as usize;
+            canvas.pixels[target_start..target_start + row_bytes].copy_from_slice(&self.pixels[source_start..source_start + row_bytes]);
+        }
+        canvas
+    }
+
+    /// Same size canvas, centered (rounding towards the top left).
+    fn centered(&self, width: u32, height: u32) -> RgbaImage {
+        self.place(width, height, center_offset(self.width, width), center_offset(self.height, height))
+    }
+}
+
+fn center_offset(len: u32, canvas_len: u32) -> i64 {
+    (canvas_len as i64 - len as i64).div_euclid(2)
+}
+
+/// Float pixels with the color multiplied by the alpha, so filtering doesn't bleed the color of
+/// transparent pixels into the visible ones.
+struct Premultiplied {
+    width: usize,
+    height: usize,
+    data: Vec<[f32; 4]>,
+}
+
+impl Premultiplied {
+    fn from_image(image: &RgbaImage) -> Self {
+        let data = image.pixels.chunks_exact(4).map(|p| {
+            let alpha = p[3] as f32 / 255.0;
+            [p[0] as f32 * alpha, p[1] as f32 * alpha, p[2] as f32 * alpha, p[3] as f32]
+        }).collect();
+        Premultiplied { width: image.width as usize, height: image.height as usize, data }
+    }
+
+    fn into_image(self) -> RgbaImage {
+        let pixels = self.data.iter().flat_map(|p| {
+            let alpha = p[3].round().clamp(0.0, 255.0);
+            if alpha == 0.0 {
+                return [0, 0, 0, 0];
+            }
+            let channel = |c: f32| (c * 255.0 / alpha).round().clamp(0.0, 255.0) as u8;
+            [channel(p[0]), channel(p[1]), channel(p[2]), alpha as u8]
+        }).collect();
+        RgbaImage { width: self.width as u32, height: self.height as u32, pixels }
+    }
+
+    /// Runs `filter` on every row (or every column), handing it the line and a buffer for the result.
+    fn map_lines(&self, horizontal: bool, new_len: usize, filter: impl Fn(&[[f32; 4]], &mut [[f32; 4]])) -> Premultiplied {
+        let (width, height) = if horizontal { (new_len, self.height) } else { (self.width, new_len) };
+        let mut result = Premultiplied { width, height, data: vec![[0.0; 4]; width * height] };
+        if horizontal {
+            for y in 0..self.height {
+                filter(&self.data[y * self.width..(y + 1) * self.width], &mut result.data[y * width..(y + 1) * width]);
+            }
+        } else {
+            let mut column = vec![[0.0; 4]; self.height];
+            let mut output = vec![[0.0; 4]; new_len];
+            for x in 0..self.width {
+                for (y, pixel) in column.iter_mut().enumerate() {
+                    *pixel = self.data[y * self.width + x];
+                }
+                filter(&column, &mut output);
+                for (y, pixel) in output.iter().enumerate() {
+                    result.data[y * width + x] = *pixel;
+                }
+            }
+        }
+        result
+    }
+
+    fn map_both(self, filter: impl Fn(&[[f32; 4]], &mut [[f32; 4]])) -> Premultiplied {
+        let (width, height) = (self.width, self.height);
+        self.map_lines(true, width, &filter).map_lines(false, height, &filter)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Smoothing {
+    Nearest,
+    Bilinear,
+    /// Catmull-Rom.
+    Bicubic,
+    Hermite,
+    /// Cubic B-spline, the softest of the lot.
+    Bezier,
+}
+
+impl Smoothing {
+    fn support(self) -> f32 {
+        match self {
+            Smoothing::Nearest => 0.5,
+            Smoothing::Bilinear | Smoothing::Hermite => 1.0,
+            Smoothing::Bicubic | Smoothing::Bezier => 2.0,
+        }
+    }
+
+    fn kernel(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Smoothing::Nearest => if x <= 0.5 { 1.0 } else { 0.0 },
+            Smoothing::Bilinear => (1.0 - x).max(0.0),
+            Smoothing::Hermite => if x < 1.0 { (2.0 * x - 3.0) * x * x + 1.0 } else { 0.0 },
+            Smoothing::Bicubic => {
+                if x < 1.0 {
+                    (1.5 * x - 2.5) * x * x + 1.0
+                } else if x < 2.0 {
+                    ((-0.5 * x + 2.5) * x - 4.0) * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            Smoothing::Bezier => {
+                if x < 1.0 {
+                    (0.5 * x - 1.0) * x * x + 2.0 / 3.0
+                } else if x < 2.0 {
+                    (2.0 - x).powi(3) / 6.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// For every destination pixel, the first source pixel it reads and the weight of each one from there.
+fn resample_weights(source_len: usize, target_len: usize, smoothing: Smoothing) -> Vec<(usize, Vec<f32>)> {
+    let scale = source_len as f32 / target_len as f32;
+    (0..target_len).map(|i| {
+        let center = (i as f32 + 0.5) * scale;
+        if smoothing == Smoothing::Nearest {
+            return ((center as usize).min(source_len - 1), vec![1.0]);
+        }
+        // Shrinking widens the kernel so every source pixel counts.
+        let stretch = scale.max(1.0);
+        let radius = smoothing.support() * stretch;
+        let first = (center - radius - 0.5).floor().max(0.0) as usize;
+        let last = ((center + radius - 0.5).ceil() as usize).min(source_len - 1);
+        let mut weights: Vec<f32> = (first..=last)
+            .map(|j| smoothing.kernel((j as f32 + 0.5 - center) / stretch))
+            .collect();
+        let total: f32 = weights.iter().sum();
+        if total.abs() > f32::EPSILON {
+            weights.iter_mut().for_each(|w| *w /= total);
+        }
+        (first, weights)
+    }).collect()
+}
+
+fn resample_line(weights: &[(usize, Vec<f32>)]) -> impl Fn(&[[f32; 4]], &mut [[f32; 4]]) + '_ {
+    move |source, target| {
+        for (pixel, (first, pixel_weights)) in target.iter_mut().zip(weights) {
+            let mut sum = [0.0; 4];
+            for (source_pixel, weight) in source[*first..].iter().zip(pixel_weights) {
+                for channel in 0..4 {
+                    sum[channel] += source_pixel[channel] * weight;
+                }
+            }
+            // Bicubic overshoots, keep the color under the alpha so it stays valid premultiplied.
+            let alpha = sum[3].clamp(0.0, 255.0);
+            sum = [sum[0].clamp(0.0, alpha), sum[1].clamp(0.0, alpha), sum[2].clamp(0.0, alpha), alpha];
+            *pixel = sum;
+        }
+    }
+}
+
+pub fn resample(image: &RgbaImage, width: u32, height: u32, smoothing: Smoothing) -> RgbaImage {
+    if width == image.width && height == image.height {
+        return image.clone();
+    }
+    let source = Premultiplied::from_image(image);
+    let horizontal = resample_weights(source.width, width as usize, smoothing);
+    let vertical = resample_weights(source.height, height as usize, smoothing);
+    source.map_lines(true, width as usize, resample_line(&horizontal))
+        .map_lines(false, height as usize, resample_line(&vertical))
+        .into_image()
+}
+
+/// Average of the `radius` pixels on each side, edge pixels repeating past the border.
+fn box_blur_line(radius: usize) -> impl Fn(&[[f32; 4]], &mut [[f32; 4]]) {
+    move |source, target| {
+        let last = source.len() as i64 - 1;
+        let at = |i: i64| source[i.clamp(0, last) as usize];
+        let mut sum = [0.0; 4];
+        for i in -(radius as i64)..=radius as i64 {
+            let pixel = at(i);
+            (0..4).for_each(|c| sum[c] += pixel[c]);
+        }
+        let count = (2 * radius + 1) as f32;
+        for (i, pixel) in target.iter_mut().enumerate() {
+            *pixel = sum.map(|s| s / count);
+            let (leaving, entering) = (at(i as i64 - radius as i64), at(i as i64 + radius as i64 + 1));
+            (0..4).for_each(|c| sum[c] += entering[c] - leaving[c]);
+        }
+    }
+}
+
+fn gaussian_line(weights: &[f32]) -> impl Fn(&[[f32; 4]], &mut [[f32; 4]]) + '_ {
+    move |source, target| {
+        let last = source.len() as i64 - 1;
+        let reach = (weights.len() / 2) as i64;
+        for (i, pixel) in target.iter_mut().enumerate() {
+            let mut sum = [0.0; 4];
+            for (k, weight) in weights.iter().enumerate() {
+                let source_pixel = source[(i as i64 + k as i64 - reach).clamp(0, last) as usize];
+                (0..4).for_each(|c| sum[c] += source_pixel[c] * weight);
+            }
+            *pixel = sum;
+        }
+    }
+}
+
+/// Three box blur passes, close to a gaussian of the same radius at a fraction of the cost.
+pub fn fast_blur(image: &RgbaImage, radius: u32) -> RgbaImage {
+    let mut data = Premultiplied::from_image(image);
+    for _ in 0..3 {
+        data = data.map_both(box_blur_line(radius as usize));
+    }
+    data.into_image()
+}
+
+/// The radius is the standard deviation, the kernel reaching 2.57 times as far like Jimp's.
+pub fn gaussian_blur(image: &RgbaImage, radius: u32) -> RgbaImage {
+    let sigma = radius as f32;
+    let reach = (sigma * 2.57).ceil() as i64;
+    let mut weights: Vec<f32> = (-reach..=reach).map(|d| (-((d * d) as f32) / (2.0 * sigma * sigma)).exp()).collect();
+    let total: f32 = weights.iter().sum();
+    weights.iter_mut().for_each(|w| *w /= total);
+    Premultiplied::from_image(image).map_both(gaussian_line(&weights)).into_image()
+}
+
+pub fn alpha_threshold(mut image: RgbaImage, threshold: u8, min: u8, max: u8) -> RgbaImage {
+    for pixel in image.pixels.chunks_exact_mut(4) {
+        pixel[3] = if pixel[3] >= threshold { max } else { min };
+    }
+    image
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ResizeMode {
+    Stretch,
+    /// Scaled to fill the size, the overflow cropped evenly.
+    Cover,
+    /// Scaled to fit in the size, centered on a transparent canvas.
+    Contain,
+    /// Not scaled, the canvas is cropped or extended from the top left corner.
+    AlignTopLeft,
+    /// Not scaled, the canvas is cropped or extended evenly on each side.
+    AlignCenter,
+}
+
+/// A number of pixels, or a string: pixels, a percentage of the frame size (`"50%"`) or `"auto"`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Dimension {
+    Pixels(f64),
+    Text(String),
+}
+
+impl Dimension {
+    /// `None` for auto.
+    fn resolve(&self, source: u32) -> Result<Option<f64>, AppError> {
+        let text = match self {
+            Dimension::Pixels(pixels) => return Ok(Some(*pixels)),
+            Dimension::Text(text) => text.trim(),
+        };
+        if text.eq_ignore_ascii_case("auto") {
+            return Ok(None);
+        }
+        let value = match text.strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f64>().map(|p| source as f64 * p / 100.0),
+            None => text.parse::<f64>(),
+        };
+        value.map(Some).map_err(|_| AppError::new(AppErrorKind::InvalidInput, format!("'{}' is not a size, use pixels, a percentage or auto", text)))
+    }
+
+    fn is_auto(&self) -> bool {
+        matches!(self, Dimension::Text(text) if text.trim().eq_ignore_ascii_case("auto"))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ResizeSize {
+    width: Dimension,
+    height: Dimension,
+}
+
+fn pixel_size(value: f64) -> Result<u32, AppError> {
+    let rounded = value.round();
+    if !(1.0..=MAX_IMAGE_SIZE as f64).contains(&rounded) {
+        return Err(AppError::new(AppErrorKind::InvalidInput, format!("{} is not a valid image size, it must be between 1 and {} pixels", value, MAX_IMAGE_SIZE)));
+    }
+    Ok(rounded as u32)
+}
+
+impl ResizeSize {
+    /// An auto side keeps the aspect ratio of the frame.
+    fn resolve(&self, width: u32, height: u32) -> Result<(u32, u32), AppError> {
+        let (new_width, new_height) = match (self.width.resolve(width)?, self.height.resolve(height)?) {
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (w, height as f64 * w / width as f64),
+            (None, Some(h)) => (width as f64 * h / height as f64, h),
+            (None, None) => return Err(AppError::new(AppErrorKind::InvalidInput, "Width and height can't both be auto")),
+        };
+        Ok((pixel_size(new_width)?, pixel_size(new_height)?))
+    }
+}
+
+/// One step of an image action chain, with the parameters of the matching Jimp action it replaces.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum ImageAction {
+    Resize { size: ResizeSize, mode: ResizeMode, smoothing: Smoothing },
+    FastBlur { radius: u32 },
+    GaussianBlur { radius: u32 },
+    /// Alpha at or above `threshold` becomes `max`, the rest `min`, all three 0-255.
+    AlphaThreshold { threshold: f64, min: f64, max: f64 },
+    /// The frame becomes a copy of the PNG at `path`.
+    ReplaceImage { path: String },
+    /// Crops to the pixels with an alpha above `cutoff` (0-255), keeping `padding` transparent pixels around them.
+    /// With `shared_box`, every frame of an animation is cropped to the box holding all of them, so they keep
+    /// lining up. Fully transparent frames are left alone.
+    Trim {
+        #[serde(default)]
+        cutoff: f64,
+        #[serde(default)]
+        padding: u32,
+        #[serde(default)]
+        shared_box: bool,
+    },
+    /// Traces the pixels with an alpha above `threshold` (0-255) into the collision polygon of the frame, at most
+    /// `max_vertices` points. The pixels are left alone, fully transparent frames keep their polygon.
+    TraceCollision {
+        #[serde(default)]
+        threshold: f64,
+        max_vertices: usize,
+        shape: PolygonShape,
+    },
+}
+
+fn channel_value(value: f64) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+impl ImageAction {
+    /// Catches what doesn't depend on the frame before any file is read.
+    pub fn validate(&self) -> Result<(), AppError> {
+        match self {
+            ImageAction::Resize { size, .. } if size.width.is_auto() && size.height.is_auto() => {
+                Err(AppError::new(AppErrorKind::InvalidInput, "Width and height can't both be auto"))
+            }
+            ImageAction::FastBlur { radius: 0 } | ImageAction::GaussianBlur { radius: 0 } => {
+                Err(AppError::new(AppErrorKind::InvalidInput, "The blur radius must be at least 1"))
+            }
+            ImageAction::GaussianBlur { radius } if *radius > MAX_GAUSSIAN_RADIUS => {
+                Err(AppError::new(AppErrorKind::InvalidInput, format!("A gaussian blur radius of {} is over the {} pixels limit", radius, MAX_GAUSSIAN_RADIUS)))
+            }
+            ImageAction::ReplaceImage { path } if !Path::new(path).is_file() => Err(AppError::not_found(path)),
+            ImageAction::Trim { padding, .. } if *padding >= MAX_IMAGE_SIZE / 2 => {
+                Err(AppError::new(AppErrorKind::InvalidInput, format!("A padding of {} pixels would make the frames too large", padding)))
+            }
+            ImageAction::TraceCollision { max_vertices, .. } if *max_vertices < 3 => {
+                Err(AppError::new(AppErrorKind::InvalidInput, "A collision polygon needs at least 3 points"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The new image and where the pixels of the old one ended up in it.
+    pub fn apply(&self, image: RgbaImage) -> Result<(RgbaImage, PixelMapping), AppError> {
+        Ok(match self {
+            ImageAction::Resize { size, mode, smoothing } => resize(&image, size, *mode, *smoothing)?,
+            ImageAction::FastBlur { radius } => (fast_blur(&image, *radius), PixelMapping::IDENTITY),
+            ImageAction::GaussianBlur { radius } => (gaussian_blur(&image, *radius), PixelMapping::IDENTITY),
+            ImageAction::AlphaThreshold { threshold, min, max } => {
+                (alpha_threshold(image, channel_value(*threshold), channel_value(*min), channel_value(*max)), PixelMapping::IDENTITY)
+            }
+            ImageAction::ReplaceImage { path } => {
+                // Nothing relates the old pixels to the new ones, points keep their place relative to the frame.
+                let replacement = RgbaImage::read_png(Path::new(path))?;
+                let mapping = PixelMapping::scale(replacement.width as f64 / image.width as f64, replacement.height as f64 / image.height as f64);
+                (replacement, mapping)
+            }
+            ImageAction::Trim { cutoff, padding, .. } => {
+                let bounds = opaque_bounds(&image, channel_value(*cutoff));
+                trim(image, bounds, *padding)?
+            }
+            ImageAction::TraceCollision { .. } => (image, PixelMapping::IDENTITY),
+        })
+    }
+
+    fn changes_pixels(&self) -> bool {
+        !matches!(self, ImageAction::TraceCollision { .. })
+    }
+
+    /// Actions needing every frame of an animation at once.
+    fn is_shared(&self) -> bool {
+        matches!(self, ImageAction::Trim { shared_box: true, .. })
+    }
+}
+
+/// Where a pixel coordinate of an image lands after an action: `x * scale_x + offset_x`, same for `y`.
+/// Coordinates are measured from the top left corner of the image, so a 10 pixels wide image spans 0 to 10.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelMapping {
+    pub scale_x: f64,
+    pub scale_y: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+}
+
+impl PixelMapping {
+    pub const IDENTITY: PixelMapping = PixelMapping { scale_x: 1.0, scale_y: 1.0, offset_x: 0.0, offset_y: 0.0 };
+
+    pub fn scale(scale_x: f64, scale_y: f64) -> Self {
+        PixelMapping { scale_x, scale_y, ..Self::IDENTITY }
+    }
+
+    pub fn offset(offset_x: f64, offset_y: f64) -> Self {
+        PixelMapping { offset_x, offset_y, ..Self::IDENTITY }
+    }
+
+    /// This mapping followed by `next`.
+    pub fn then(self, next: PixelMapping) -> Self {
+        PixelMapping {
+            scale_x: self.scale_x * next.scale_x,
+            scale_y: self.scale_y * next.scale_y,
+            offset_x: self.offset_x * next.scale_x + next.offset_x,
+            offset_y: self.offset_y * next.scale_y + next.offset_y,
+        }
+    }
+
+    pub fn map(&self, x: f64, y: f64) -> (f64, f64) {
+        (x * self.scale_x + self.offset_x, y * self.scale_y + self.offset_y)
+    }
+}
+
+fn scaled_len(len: u32, scale: f64) -> u32 {
+    ((len as f64 * scale).round() as u32).clamp(1, MAX_IMAGE_SIZE)
+}
+
+pub fn resize(image: &RgbaImage, size: &ResizeSize, mode: ResizeMode, smoothing: Smoothing) -> Result<(RgbaImage, PixelMapping), AppError> {
+    let (width, height) = size.resolve(image.width, image.height)?;
+    let scale_x = width as f64 / image.width as f64;
+    let scale_y = height as f64 / image.height as f64;
+    let centering = |inner_width: u32, inner_height: u32| {
+        PixelMapping::offset(center_offset(inner_width, width) as f64, center_offset(inner_height, height) as f64)
+    };
+    Ok(match mode {
+        ResizeMode::Stretch => (resample(image, width, height, smoothing), PixelMapping::scale(scale_x, scale_y)),
+        ResizeMode::Cover | ResizeMode::Contain => {
+            let scale = if mode == ResizeMode::Cover { scale_x.max(scale_y) } else { scale_x.min(scale_y) };
+            let scaled = resample(image, scaled_len(image.width, scale), scaled_len(image.height, scale), smoothing);
+            // The rounded size, not `scale`, is what the pixels were stretched by.
+            let mapping = PixelMapping::scale(scaled.width as f64 / image.width as f64, scaled.height as f64 / image.height as f64)
+                .then(centering(scaled.width, scaled.height));
+            (scaled.centered(width, height), mapping)
+        }
+        ResizeMode::AlignTopLeft => (image.place(width, height, 0, 0), PixelMapping::IDENTITY),
+        ResizeMode::AlignCenter => (image.centered(width, height), centering(image.width, image.height)),
+    })
+}
+
+/// A pixel rectangle, right and bottom excluded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+impl Bounds {
+    fn union(self, other: Bounds) -> Bounds {
+        Bounds {
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+}
+
+/// The smallest rectangle holding every pixel with an alpha above `cutoff`, `None` when there's none.
+pub fn opaque_bounds(image: &RgbaImage, cutoff: u8) -> Option<Bounds> {
+    let mut bounds: Option<Bounds> = None;
+    for (y, row) in image.pixels.chunks_exact(image.width as usize * 4).enumerate() {
+        let visible = |p: &[u8]| p[3] > cutoff;
+        let Some(first) = row.chunks_exact(4).position(visible) else { continue };
+        let last = row.chunks_exact(4).rposition(visible).unwrap_or(first);
+        let row_bounds = Bounds { left: first as u32, top: y as u32, right: last as u32 + 1, bottom: y as u32 + 1 };
+        bounds = Some(bounds.map_or(row_bounds, |b| b.union(row_bounds)));
+    }
+    bounds
+}
+
+/// Crops (or extends, for padding and shared boxes larger than the frame) to `bounds` grown by `padding` on every side.
+pub fn trim(image: RgbaImage, bounds: Option<Bounds>, padding: u32) -> Result<(RgbaImage, PixelMapping), AppError> {
+    let Some(bounds) = bounds else {
+        return Ok((image, PixelMapping::IDENTITY));
+    };
+    let width = bounds.right - bounds.left + 2 * padding;
+    let height = bounds.bottom - bounds.top + 2 * padding;
+    if width > MAX_IMAGE_SIZE || height > MAX_IMAGE_SIZE {
+        return Err(AppError::new(AppErrorKind::InvalidInput, format!("A {}x{} frame is larger than the {} pixels limit", width, height, MAX_IMAGE_SIZE)));
+    }
+    let x = padding as i64 - bounds.left as i64;
+    let y = padding as i64 - bounds.top as i64;
+    Ok((image.place(width, height, x, y), PixelMapping::offset(x as f64, y as f64)))
+}
+
+/// A frame PNG and the JSON pointer of its entry in the object type (an animation frame, or `/image`).
+#[derive(Debug, Clone)]
+pub struct FrameTarget {
+    pub object_type: String,
+    pub path: PathBuf,
+    pub pointer: String,
+}
+
+fn collect_animation_frames(
+    animations: &Value,
+    pointer: &str,
+    object_type: &str,
+    images_dir: &Path,
+    animation_name: Option<&str>,
+    frame_index: Option<usize>,
+    targets: &mut Vec<FrameTarget>,
+) {
+    let items = animations.get("items").and_then(|i| i.as_array()).into_iter().flatten();
+    for (i, animation) in items.enumerate() {
+        let name = animation.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+        if animation_name.is_some_and(|wanted| wanted != name) {
+            continue;
+        }
+        let frames = animation.get("frames").and_then(|f| f.as_array()).map(|f| f.len()).unwrap_or(0);
+        for frame in (0..frames).filter(|frame| frame_index.is_none() || frame_index == Some(*frame)) {
+            targets.push(FrameTarget {
+                object_type: object_type.to_string(),
+                path: images_dir.join(frame_image_name(object_type, name, frame)),
+                pointer: format!("{}/items/{}/frames/{}", pointer, i, frame),
+            });
+        }
+    }
+    let subfolders = animations.get("subfolders").and_then(|s| s.as_array()).into_iter().flatten();
+    for (i, subfolder) in subfolders.enumerate() {
+        let subfolder_pointer = format!("{}/subfolders/{}", pointer, i);
+        collect_animation_frames(subfolder, &subfolder_pointer, object_type, images_dir, animation_name, frame_index, targets);
+    }
+}
+
+/// Frame PNGs of an object type, optionally only one animation or one frame of it.
+/// Single image types (Tiled Background, 9-patch...) have their one image whatever is asked.
+pub fn object_type_frames(project_base_path: &Path, object_type: &str, definition: &Value, animation: Option<&str>, frame: Option<usize>) -> Vec<FrameTarget> {
+    let images_dir = project_base_path.join("images");
+    let mut targets = Vec::new();
+    if let Some(animations) = definition.get("animations") {
+        collect_animation_frames(animations, "/animations", object_type, &images_dir, animation, frame, &mut targets);
+    } else if definition.get("image").is_some() {
+        targets.push(FrameTarget {
+            object_type: object_type.to_string(),
+            path: images_dir.join(format!("{}.png", object_type.to_lowercase())),
+            pointer: "/image".to_string(),
+        });
+    }
+    targets
+}
+
+/// A processed frame, encoded and waiting to be written.
+pub struct EncodedFrame {
+    pub source_width: u32,
+    pub source_height: u32,
+    pub width: u32,
+    pub height: u32,
+    /// From the pixels of the source PNG to the processed ones, the whole chain composed.
+    pub mapping: PixelMapping,
+    /// Traced collision polygon, in pixels of the processed frame.
+    pub collision_polygon: Option<Vec<Point>>,
+    /// `None` when no action touched the pixels, the PNG is then left as is.
+    pub png: Option<Vec<u8>>,
+}
+
+struct FrameState {
+    image: RgbaImage,
+    source_width: u32,
+    source_height: u32,
+    mapping: PixelMapping,
+    collision_polygon: Option<Vec<Point>>,
+}
+
+impl FrameState {
+    /// `shared_bounds` is the box of the whole group, for shared trims.
+    fn apply(self, action: &ImageAction, shared_bounds: Option<Bounds>) -> Result<FrameState, AppError> {
+        if let ImageAction::TraceCollision { threshold, max_vertices, shape } = action {
+            let traced = trace_polygon(&self.image, channel_value(*threshold), *max_vertices, *shape);
+            return Ok(FrameState { collision_polygon: traced.or(self.collision_polygon), ..self });
+        }
+        let (image, mapping) = match action {
+            ImageAction::Trim { padding, shared_box: true, .. } => trim(self.image, shared_bounds, *padding)?,
+            _ => action.apply(self.image)?,
+        };
+        // A polygon traced earlier in the chain follows the pixels.
+        let collision_polygon = self.collision_polygon.map(|polygon| polygon.into_iter().map(|(x, y)| mapping.map(x, y)).collect());
+        Ok(FrameState { image, mapping: self.mapping.then(mapping), collision_polygon, ..self })
+    }
+}
+
+/// Runs the chain on frames that are processed together, the frames of one animation when an action is shared.
+/// A frame that fails drops out of the group, shared actions only look at the others.
+fn process_group(paths: &[PathBuf], actions: &[ImageAction]) -> Vec<Result<EncodedFrame, AppError>> {
+    let mut states: Vec<Result<FrameState, AppError>> = paths.iter().map(|path| {
+        let image = RgbaImage::read_png(path)?;
+        Ok(FrameState { source_width: image.width, source_height: image.height, image, mapping: PixelMapping::IDENTITY, collision_polygon: None })
+    }).collect();
+    for action in actions {
+        let shared_bounds = match action {
+            ImageAction::Trim { cutoff, shared_box: true, .. } => states.iter().flatten()
+                .filter_map(|state| opaque_bounds(&state.image, channel_value(*cutoff)))
+                .reduce(Bounds::union),
+            _ => None,
+        };
+        states = states.into_iter().map(|state| state?.apply(action, shared_bounds)).collect();
+    }
+    let changes_pixels = actions.iter().any(|action| action.changes_pixels());
+    states.into_iter().zip(paths).map(|(state, path)| {
+        let state = state?;
+        Ok(EncodedFrame {
+            source_width: state.source_width,
+            source_height: state.source_height,
+            width: state.image.width,
+            height: state.image.height,
+            mapping: state.mapping,
+            png: if changes_pixels { Some(state.image.encode_png(path)?) } else { None },
+            collision_polygon: state.collision_polygon,
+        })
+    }).collect()
+}
+
+/// Frames of the same animation share their pointer up to the frame index.
+fn animation_key(target: &FrameTarget) -> (&str, &str) {
+    let pointer = target.pointer.rsplit_once("/frames/").map_or(target.pointer.as_str(), |(animation, _)| animation);
+    (&target.object_type, pointer)
+}
+
+/// Moves a point stored as a fraction of the frame size (what Construct uses for the origin, image points
+/// and collision polygon) so it stays on the same pixel of the processed frame.
+fn remap_normalized(frame: &EncodedFrame, x: f64, y: f64) -> (f64, f64) {
+    let (x, y) = frame.mapping.map(x * frame.source_width as f64, y * frame.source_height as f64);
+    (x / frame.width as f64, y / frame.height as f64)
+}
+
+fn remap_point_fields(point: &mut Map<String, Value>, x_key: &str, y_key: &str, frame: &EncodedFrame) {
+    let (Some(x), Some(y)) = (point.get(x_key).and_then(|v| v.as_f64()), point.get(y_key).and_then(|v| v.as_f64())) else { return };
+    let (x, y) = remap_normalized(frame, x, y);
+    point.insert(x_key.to_string(), Value::from(x));
+    point.insert(y_key.to_string(), Value::from(y));
+}
+
+/// Collision polygon points come as `[x, y]` pairs, `{x, y}` objects or one flat list, they're written back the same way.
+fn remap_polygon_points(points: &mut [Value], frame: &EncodedFrame) {
+    if points.iter().all(|p| p.is_number()) {
+        for pair in points.chunks_exact_mut(2) {
+            let (Some(x), Some(y)) = (pair[0].as_f64(), pair[1].as_f64()) else { continue };
+            let (x, y) = remap_normalized(frame, x, y);
+            pair[0] = Value::from(x);
+            pair[1] = Value::from(y);
+        }
+        return;
+    }
+    for point in points {
+        match point {
+            Value::Array(pair) if pair.len() >= 2 => {
+                let (Some(x), Some(y)) = (pair[0].as_f64(), pair[1].as_f64()) else { continue };
+                let (x, y) = remap_normalized(frame, x, y);
+                pair[0] = Value::from(x);
+                pair[1] = Value::from(y);
+            }
+            Value::Object(point) => remap_point_fields(point, "x", "y", frame),
+            _ => {}
+        }
+    }
+}
+
+/// Writes a traced polygon as the collision polygon of a frame entry, in the point format it already uses
+/// (`[x, y]` pairs when it has none), and turns it on.
+fn set_collision_polygon(entry: &mut Map<String, Value>, polygon: &[Point], frame: &EncodedFrame) {
+    let normalized = polygon.iter().map(|(x, y)| (x / frame.width as f64, y / frame.height as f64));
+    let existing = entry.get("collisionPoly").and_then(|c| c.get("points")).and_then(|p| p.as_array()).and_then(|p| p.first());
+    let points: Vec<Value> = match existing {
+        Some(Value::Number(_)) => normalized.flat_map(|(x, y)| [Value::from(x), Value::from(y)]).collect(),
+        Some(Value::Object(_)) => normalized.map(|(x, y)| json!({ "x": x, "y": y })).collect(),
+        _ => normalized.map(|(x, y)| json!([x, y])).collect(),
+    };
+    match entry.get_mut("collisionPoly").and_then(|c| c.as_object_mut()) {
+        Some(collision_poly) => {
+            collision_poly.insert("points".to_string(), Value::Array(points));
+        }
+        None => {
+            entry.insert("collisionPoly".to_string(), json!({ "points": points }));
+        }
+    }
+    if let Some(use_collision_poly) = entry.get_mut("useCollisionPoly") {
+        *use_collision_poly = Value::Bool(true);
+    }
+}
+
+/// Writes the new size of a frame entry and moves its origin, image points and collision polygon along with its pixels.
+/// The size is only written when it changed, and on entries that hold one: animation frames always do.
+fn update_frame_entry(entry: &mut Map<String, Value>, frame: &EncodedFrame, is_animation_frame: bool) {
+    let resized = (frame.width, frame.height) != (frame.source_width, frame.source_height);
+    for (key, len) in [("width", frame.width), ("height", frame.height)] {
+        if resized && (is_animation_frame || entry.contains_key(key)) {
+            entry.insert(key.to_string(), Value::from(len));
+        }
+    }
+    let (left, top) = remap_normalized(frame, 0.0, 0.0);
+    let (right, bottom) = remap_normalized(frame, 1.0, 1.0);
+    if [left, top, right - 1.0, bottom - 1.0].iter().all(|d| d.abs() < 1e-9) {
+        // Stretched or untouched, every point keeps its fraction of the frame.
+        return;
+    }
+    remap_point_fields(entry, "originX", "originY", frame);
+    if let Some(image_points) = entry.get_mut("imagePoints").and_then(|p| p.as_array_mut()) {
+        for point in image_points.iter_mut().filter_map(|p| p.as_object_mut()) {
+            remap_point_fields(point, "x", "y", frame);
+        }
+    }
+    if let Some(points) = entry.get_mut("collisionPoly").and_then(|c| c.get_mut("points")).and_then(|p| p.as_array_mut()) {
+        remap_polygon_points(points, frame);
+    }
+}
+
+/// Runs `actions` on every target on the blocking pool, as many frames at once as there are cores, or as many
+/// animations when an action is shared between frames. Results come back in the order of `targets`.
+pub async fn process_frames(
+    targets: Vec<FrameTarget>,
+    actions: Arc<[ImageAction]>,
+    mut on_progress: impl FnMut(f64, &str),
+) -> Result<Vec<(FrameTarget, Result<EncodedFrame, AppError>)>, AppError> {
+    let total = targets.len();
+    let shared = actions.iter().any(|action| action.is_shared());
+    let mut groups: Vec<Vec<FrameTarget>> = Vec::new();
+    for target in targets {
+        match groups.last_mut() {
+            Some(group) if shared && animation_key(&group[0]) == animation_key(&target) => group.push(target),
+            _ => groups.push(vec![target]),
+        }
+    }
+    let workers = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+    let mut pending = groups.into_iter().enumerate();
+    let mut set = JoinSet::new();
+    let spawn = |set: &mut JoinSet<_>, (order, group): (usize, Vec<FrameTarget>)| {
+        let actions = actions.clone();
+        set.spawn_blocking(move || {
+            let paths: Vec<PathBuf> = group.iter().map(|target| target.path.clone()).collect();
+            let results = process_group(&paths, &actions);
+            (order, group.into_iter().zip(results).collect::<Vec<_>>())
+        });
+    };
+    pending.by_ref().take(workers).for_each(|job| spawn(&mut set, job));
+
+    let mut done = 0;
+    let mut results = Vec::new();
+    while let Some(joined) = set.join_next().await {
+        let (order, group) = joined?;
+        if let Some(job) = pending.next() {
+            spawn(&mut set, job);
+        }
+        done += group.len();
+        let file_name = group.last().and_then(|(target, _)| target.path.file_name()).map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        on_progress(done as f64 / total.max(1) as f64, &file_name);
+        results.push((order, group));
+    }
+    results.sort_by_key(|(order, _)| *order);
+    Ok(results.into_iter().flat_map(|(_, group)| group).collect())
+}
+
+/// The frames an action chain runs on.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ImageTarget {
+    Frame { object_type: String, animation: String, frame: usize },
+    Animation { object_type: String, animation: String },
+    /// Every animation, or the one image of single image types.
+    ObjectType { object_type: String },
+    /// Every animation of every member.
+    Family { family: String },
+    /// Every object type of a folder of the c3proj tree and its subfolders, `path` being slash separated
+    /// like "Enemies/Flying". An empty path is the whole project.
+    Folder { path: String },
+}
+
+impl ImageTarget {
+    fn object_type_names(&self, project_base_path: &Path, project: &Project) -> Result<Vec<String>, AppError> {
+        match self {
+            ImageTarget::Frame { object_type, .. } | ImageTarget::Animation { object_type, .. } | ImageTarget::ObjectType { object_type } => {
+                Ok(vec![object_type.clone()])
+            }
+            ImageTarget::Family { family } => {
+                let path = project.family_path(family)
+                    .ok_or_else(|| AppError::new(AppErrorKind::NotFound, format!("No family named '{}'", family)))?;
+                let (definition, _) = read_json_file(&project_base_path.join(path))?;
+                let members = definition.get("members").and_then(|m| m.as_array()).into_iter().flatten();
+                Ok(members.filter_map(|m| m.as_str()).map(String::from).collect())
+            }
+            ImageTarget::Folder { path } => {
+                let folder = path.trim_matches('/');
+                let names: Vec<String> = project.object_types.items_with_paths().into_iter()
+                    .filter(|(folder_path, _)| folder.is_empty() || folder_path == folder || folder_path.starts_with(&format!("{}/", folder)))
+                    .map(|(_, name)| name.clone())
+                    .collect();
+                if names.is_empty() {
+                    return Err(AppError::new(AppErrorKind::NotFound, format!("No object type in folder '{}'", folder)));
+                }
+                Ok(names)
+            }
+        }
+    }
+
+    fn animation(&self) -> Option<&str> {
+        match self {
+            ImageTarget::Frame { animation, .. } | ImageTarget::Animation { animation, .. } => Some(animation),
+            _ => None,
+        }
+    }
+
+    fn frame(&self) -> Option<usize> {
+        match self {
+            ImageTarget::Frame { frame, .. } => Some(*frame),
+            _ => None,
+        }
+    }
+
+    /// Targets naming one object type fail when it can't be read, the others skip it with a warning.
+    fn is_single_type(&self) -> bool {
+        matches!(self, ImageTarget::Frame { .. } | ImageTarget::Animation { .. } | ImageTarget::ObjectType { .. })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ImageActionPayload {
+    project_path: String,
+    target: ImageTarget,
+    /// Applied in order, the output of one being the input of the next.
+    actions: Vec<ImageAction>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FrameResult {
+    object_type: String,
+    path: String,
+    /// Size after the chain, missing when it failed.
+    width: Option<u32>,
+    height: Option<u32>,
+    error: Option<AppError>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ImageActionResult {
+    message: String,
+    files: Vec<FrameResult>,
+}
+
+struct ObjectTypeFile {
+    path: PathBuf,
+    definition: Value,
+    changed: bool,
+}
+
+/// Applies the action chain to every frame of the target and updates the frame sizes, origins, image points and
+/// collision polygons in the object type JSON. Chains that only trace polygons leave the PNGs untouched.
+/// Frames that fail are reported in the result and left alone, the others are written together with the JSON.
+#[tauri::command]
+pub async fn apply_image_actions(app: tauri::AppHandle, window: tauri::Window, payload: ImageActionPayload) -> Result<WithWarnings<ImageActionResult>, AppError> {
+    if payload.actions.is_empty() {
+        return Err(AppError::new(AppErrorKind::InvalidInput, "No image action to apply"));
+    }
+    payload.actions.iter().try_for_each(|action| action.validate())?;
+    let project_base_path = PathBuf::from(&payload.project_path);
+    let project = Project::load(&project_base_path)?;
+    let mut warnings = Vec::new();
+    let mut type_files: HashMap<String, ObjectTypeFile> = HashMap::new();
+    let mut targets = Vec::new();
+    for name in payload.target.object_type_names(&project_base_path, &project)? {
+        let loaded = project.object_type_path(&name)
+            .ok_or_else(|| AppError::new(AppErrorKind::NotFound, format!("No object type named '{}'", name)))
+            .and_then(|path| {
+                let path = project_base_path.join(path);
+                read_json_file(&path).map(|(definition, _)| (path, definition))
+            });
+        let (path, definition) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) if payload.target.is_single_type() => return Err(e),
+            Err(e) => {
+                warnings.push(e);
+                continue;
+            }
+        };
+        targets.extend(object_type_frames(&project_base_path, &name, &definition, payload.target.animation(), payload.target.frame()));
+        type_files.insert(name, ObjectTypeFile { path, definition, changed: false });
+    }
+    if targets.is_empty() {
+        return Err(AppError::new(AppErrorKind::InvalidInput, "No frame matches the target"));
+    }
+
+    window.emit("progress", Payload { progress: 0.0, filename: "".into(), done: false, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+    let results = process_frames(targets, payload.actions.into(), |progress, filename| {
+        window.emit("progress", Payload { progress, filename: filename.into(), done: false, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+    }).await;
+    window.emit("progress", Payload { progress: 1.0, filename: "".into(), done: true, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+
+    let mut files = Vec::new();
+    let mut changes = ChangeSet::new(&project_base_path, "apply_image_actions");
+    for (target, result) in results? {
+        let path = target.path.to_string_lossy().into_owned();
+        let encoded = match result {
+            Ok(encoded) => encoded,
+            Err(e) => {
+                files.push(FrameResult { object_type: target.object_type, path, width: None, height: None, error: Some(e) });
+                continue;
+            }
+        };
+        if let Some(type_file) = type_files.get_mut(&target.object_type) {
+            if let Some(entry) = type_file.definition.pointer_mut(&target.pointer).and_then(|e| e.as_object_mut()) {
+                let original = entry.clone();
+                update_frame_entry(entry, &encoded, target.pointer.contains("/frames/"));
+                match &encoded.collision_polygon {
+                    // Only animation frames have a collision polygon.
+                    Some(_) if target.pointer == "/image" => warnings.push(AppError::new(
+                        AppErrorKind::InvalidInput,
+                        format!("'{}' has no animations, its image has no collision polygon", target.object_type),
+                    ).with_file(&type_file.path)),
+                    Some(polygon) => set_collision_polygon(entry, polygon, &encoded),
+                    None => {}
+                }
+                type_file.changed |= *entry != original;
+            }
+        }
+        files.push(FrameResult { object_type: target.object_type, path, width: Some(encoded.width), height: Some(encoded.height), error: None });
+        if let Some(png) = encoded.png {
+            changes.write_bytes(target.path, png);
+        }
+    }
+    for type_file in type_files.into_values().filter(|t| t.changed) {
+        changes.write_json(type_file.path, &type_file.definition)?;
+    }
+    changes.commit(&snapshot::app_data_dir(&app)?)?;
+
+    let failed = files.iter().filter(|f| f.error.is_some()).count();
+    let message = format!("Processed {} frames, {} failed.", files.len() - failed, failed);
+    Ok(WithWarnings { value: ImageActionResult { message, files }, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(source: (u32, u32), size: (u32, u32), mapping: PixelMapping) -> EncodedFrame {
+        EncodedFrame {
+            source_width: source.0,
+            source_height: source.1,
+            width: size.0,
+            height: size.1,
+            mapping,
+            collision_polygon: None,
+            png: None,
+        }
+    }
+
+    fn entry(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    /// `width` x `height`, transparent but for an opaque white rectangle, right and bottom excluded.
+    fn image_with_rect(width: u32, height: u32, (left, top, right, bottom): (u32, u32, u32, u32)) -> RgbaImage {
+        let mut image = RgbaImage::new(width, height);
+        for y in top..bottom {
+            for x in left..right {
+                let index = (y * width + x) as usize * 4;
+                image.pixels[index..index + 4].copy_from_slice(&[255; 4]);
+            }
+        }
+        image
+    }
+
+    /// Runs one action on `image` and updates an animation frame entry accordingly.
+    fn apply_to_entry(action: &ImageAction, image: RgbaImage, frame_entry: Value) -> (RgbaImage, Value) {
+        let source = (image.width, image.height);
+        let (image, mapping) = action.apply(image).unwrap();
+        let mut frame_entry = entry(frame_entry);
+        update_frame_entry(&mut frame_entry, &encoded(source, (image.width, image.height), mapping), true);
+        (image, Value::Object(frame_entry))
+    }
+
+    fn assert_numbers(value: &Value, pointer: &str, expected: &[f64]) {
+        let actual: Vec<f64> = match value.pointer(pointer).unwrap() {
+            Value::Array(items) => items.iter().map(|v| v.as_f64().unwrap()).collect(),
+            number => vec![number.as_f64().unwrap()],
+        };
+        assert_eq!(actual.len(), expected.len(), "{}: {:?}", pointer, actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{}: {:?} instead of {:?}", pointer, actual, expected);
+        }
+    }
+
+    fn resize_to(width: f64, height: f64, mode: ResizeMode) -> ImageAction {
+        let size = ResizeSize { width: Dimension::Pixels(width), height: Dimension::Pixels(height) };
+        ImageAction::Resize { size, mode, smoothing: Smoothing::Nearest }
+    }
+
+    #[test]
+    fn trim_moves_origin_image_points_and_polygon_with_the_pixels() {
+        let trim = ImageAction::Trim { cutoff: 0.0, padding: 1, shared_box: false };
+        let frame_entry = json!({
+            "width": 10, "height": 10, "originX": 0.4, "originY": 0.6,
+            "imagePoints": [{ "name": "Gun", "x": 0.2, "y": 0.4 }],
+            "collisionPoly": { "points": [[0.2, 0.4], [0.6, 0.4], [0.6, 0.8]] }
+        });
+        let (image, frame_entry) = apply_to_entry(&trim, image_with_rect(10, 10, (2, 4, 6, 8)), frame_entry);
+        assert_eq!((image.width, image.height), (6, 6));
+        assert_numbers(&frame_entry, "/width", &[6.0]);
+        assert_numbers(&frame_entry, "/height", &[6.0]);
+        // Pixel (4, 6) of the source is pixel (3, 3) of the trimmed frame.
+        assert_numbers(&frame_entry, "/originX", &[0.5]);
+        assert_numbers(&frame_entry, "/originY", &[0.5]);
+        assert_numbers(&frame_entry, "/imagePoints/0/x", &[1.0 / 6.0]);
+        assert_numbers(&frame_entry, "/imagePoints/0/y", &[1.0 / 6.0]);
+        assert_numbers(&frame_entry, "/collisionPoly/points/1", &[5.0 / 6.0, 1.0 / 6.0]);
+        assert_numbers(&frame_entry, "/collisionPoly/points/2", &[5.0 / 6.0, 5.0 / 6.0]);
+        assert_eq!(frame_entry["imagePoints"][0]["name"], "Gun");
+    }
+
+    #[test]
+    fn validate_limits_the_blur_radius() {
+        assert!(ImageAction::GaussianBlur { radius: MAX_GAUSSIAN_RADIUS }.validate().is_ok());
+        for action in [ImageAction::GaussianBlur { radius: 0 }, ImageAction::GaussianBlur { radius: MAX_GAUSSIAN_RADIUS + 1 }, ImageAction::FastBlur { radius: 0 }] {
+            assert_eq!(action.validate().unwrap_err().kind, AppErrorKind::InvalidInput, "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn trim_leaves_transparent_frames_alone() {
+        let trim = ImageAction::Trim { cutoff: 0.0, padding: 2, shared_box: false };
+        let frame_entry = json!({ "width": 8, "height": 4, "originX": 0.25, "originY": 0.5 });
+        let (image, updated) = apply_to_entry(&trim, RgbaImage::new(8, 4), frame_entry.clone());
+        assert_eq!((image.width, image.height), (8, 4));
+        assert_eq!(updated, frame_entry);
+    }
+
+    #[test]
+    fn shared_trim_crops_every_frame_to_the_same_box() {
+        let dir = std::env::temp_dir().join(format!("construct-crawler-test-trim-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let frames = [image_with_rect(8, 8, (1, 1, 3, 3)), image_with_rect(8, 8, (4, 5, 7, 6)), RgbaImage::new(8, 8)];
+        let paths: Vec<PathBuf> = frames.iter().enumerate().map(|(i, image)| {
+            let path = dir.join(format!("frame-{}.png", i));
+            std::fs::write(&path, image.encode_png(&path).unwrap()).unwrap();
+            path
+        }).collect();
+
+        let shared = process_group(&paths, &[ImageAction::Trim { cutoff: 0.0, padding: 0, shared_box: true }]);
+        for frame in shared {
+            let frame = frame.unwrap();
+            // The box holding both rectangles, the transparent frame included so it keeps lining up.
+            assert_eq!((frame.width, frame.height), (6, 5));
+            assert_eq!(frame.mapping, PixelMapping::offset(-1.0, -1.0));
+            assert!(frame.png.is_some());
+        }
+        let separate: Vec<(u32, u32)> = process_group(&paths, &[ImageAction::Trim { cutoff: 0.0, padding: 0, shared_box: false }])
+            .into_iter().map(|frame| frame.map(|f| (f.width, f.height)).unwrap()).collect();
+        assert_eq!(separate, [(2, 2), (3, 1), (8, 8)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resize_modes_keep_origin_on_the_same_pixel() {
+        let frame_entry = json!({ "width": 4, "height": 2, "originX": 0.0, "originY": 0.0 });
+        let cases = [
+            // Stretching keeps every point at its fraction of the frame.
+            (ResizeMode::Stretch, PixelMapping::scale(2.0, 4.0), [0.0, 0.0]),
+            // Scaled by 2 to 8x4, centered vertically.
+            (ResizeMode::Contain, PixelMapping::scale(2.0, 2.0).then(PixelMapping::offset(0.0, 2.0)), [0.0, 0.25]),
+            // Scaled by 4 to 16x8, cropped evenly on both sides.
+            (ResizeMode::Cover, PixelMapping::scale(4.0, 4.0).then(PixelMapping::offset(-4.0, 0.0)), [-0.5, 0.0]),
+            (ResizeMode::AlignTopLeft, PixelMapping::IDENTITY, [0.0, 0.0]),
+            (ResizeMode::AlignCenter, PixelMapping::offset(2.0, 3.0), [0.25, 0.375]),
+        ];
+        for (mode, mapping, origin) in cases {
+            let action = resize_to(8.0, 8.0, mode);
+            let (image, mapped) = action.apply(image_with_rect(4, 2, (0, 0, 4, 2))).unwrap();
+            assert_eq!((image.width, image.height), (8, 8), "{:?}", mode);
+            assert_eq!(mapped, mapping, "{:?}", mode);
+            let (_, updated) = apply_to_entry(&action, image_with_rect(4, 2, (0, 0, 4, 2)), frame_entry.clone());
+            assert_numbers(&updated, "/originX", &origin[..1]);
+            assert_numbers(&updated, "/originY", &origin[1..]);
+            assert_numbers(&updated, "/width", &[8.0]);
+        }
+    }
+
+    #[test]
+    fn remapped_points_keep_their_format() {
+        let frame = encoded((10, 10), (5, 5), PixelMapping::offset(-5.0, -5.0));
+        let mut flat = vec![json!(0.5), json!(0.6), json!(1.0), json!(1.0)];
+        remap_polygon_points(&mut flat, &frame);
+        assert_eq!(flat, [json!(0.0), json!(0.2), json!(1.0), json!(1.0)]);
+        let mut objects = vec![json!({ "x": 0.6, "y": 0.8 })];
+        remap_polygon_points(&mut objects, &frame);
+        assert_numbers(&Value::Array(objects), "/0/x", &[0.2]);
+    }
+
+    #[test]
+    fn frame_size_is_only_written_when_it_changed() {
+        let frame = json!({ "width": 32, "height": 32, "originX": 0.5, "originY": 0.5 });
+        let mut unchanged = entry(frame.clone());
+        update_frame_entry(&mut unchanged, &encoded((32, 32), (32, 32), PixelMapping::IDENTITY), true);
+        assert_eq!(Value::Object(unchanged), frame);
+
+        let mut resized = entry(json!({ "originX": 0.5, "originY": 0.5 }));
+        update_frame_entry(&mut resized, &encoded((32, 32), (64, 16), PixelMapping::scale(2.0, 0.5)), true);
+        assert_eq!(Value::Object(resized), json!({ "originX": 0.5, "originY": 0.5, "width": 64, "height": 16 }));
+
+        // A single image entry without a size keeps none.
+        let mut image = entry(json!({ "originX": 0.5, "originY": 0.5 }));
+        update_frame_entry(&mut image, &encoded((32, 32), (64, 16), PixelMapping::scale(2.0, 0.5)), false);
+        assert_eq!(Value::Object(image), json!({ "originX": 0.5, "originY": 0.5 }));
+    }
+}
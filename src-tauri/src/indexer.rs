@@ -0,0 +1,238 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::task::JoinSet;
+
+use crate::project::{Project, ProjectFolder};
+use crate::error::{AppError, AppErrorKind};
+use crate::{read_json_file, Payload};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FrameImage {
+    animation: String,
+    frame: usize,
+    path: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ObjectTypeIndexEntry {
+    name: String,
+    path: String,
+    src: String,
+    /// Parsed JSON with `src` set on every frame (and on `image`) whose PNG exists.
+    properties: Option<Value>,
+    original_json: Option<String>,
+    frames: Vec<FrameImage>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FamilyIndexEntry {
+    name: String,
+    path: String,
+    src: String,
+    properties: Option<Value>,
+    original_json: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LayoutIndexEntry {
+    name: String,
+    path: String,
+    layer_count: usize,
+    instance_count: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProjectIndex {
+    project: Project,
+    object_types: Vec<ObjectTypeIndexEntry>,
+    families: Vec<FamilyIndexEntry>,
+    layouts: Vec<LayoutIndexEntry>,
+    warnings: Vec<AppError>,
+}
+
+enum IndexedFile {
+    ObjectType(ObjectTypeIndexEntry),
+    Family(FamilyIndexEntry),
+    Layout(LayoutIndexEntry),
+}
+
+enum IndexJob {
+    ObjectType { name: String, path: String },
+    Family { name: String, path: String },
+    Layout { name: String, path: String },
+}
+
+impl IndexJob {
+    fn label(&self) -> String {
+        match self {
+            IndexJob::ObjectType { name, path } | IndexJob::Family { name, path } => {
+                if path.is_empty() { name.clone() } else { format!("{}/{}", path, name) }
+            }
+            IndexJob::Layout { path, .. } => path.clone(),
+        }
+    }
+}
+
+fn folder_jobs(folder: &ProjectFolder<String>, make: impl Fn(String, String) -> IndexJob) -> Vec<IndexJob> {
+    folder.items_with_paths().into_iter()
+        .map(|(path, name)| make(name.clone(), path))
+        .collect()
+}
+
+/// Reads and parses a JSON file, turning every failure into a warning.
+fn read_json(file: &Path, warnings: &mut Vec<AppError>) -> Option<(Value, String)> {
+    match read_json_file(file) {
+        Ok(result) => Some(result),
+        Err(e) => {
+            warnings.push(e);
+            None
+        }
+    }
+}
+
+pub fn frame_image_name(object_type: &str, animation: &str, frame: usize) -> String {
+    format!("{}-{}-{:03}.png", object_type.to_lowercase(), animation.to_lowercase(), frame)
+}
+
+fn resolve_animation_frames(
+    animations: &mut Value,
+    object_type: &str,
+    images_dir: &Path,
+    frames: &mut Vec<FrameImage>,
+    warnings: &mut Vec<AppError>,
+) {
+    if let Some(items) = animations.get_mut("items").and_then(|i| i.as_array_mut()) {
+        for animation in items {
+            let animation_name = animation.get("name").and_then(|n| n.as_str()).unwrap_or_default().to_string();
+            if let Some(anim_frames) = animation.get_mut("frames").and_then(|f| f.as_array_mut()) {
+                for (i, frame) in anim_frames.iter_mut().enumerate() {
+                    let image_name = frame_image_name(object_type, &animation_name, i);
+                    let image_path = images_dir.join(&image_name);
+                    if image_path.exists() {
+                        let path = image_path.to_string_lossy().into_owned();
+                        if let Some(frame_obj) = frame.as_object_mut() {
+                            frame_obj.insert("src".to_string(), Value::String(path.clone()));
+                        }
+                        frames.push(FrameImage { animation: animation_name.clone(), frame: i, path });
+                    } else {
+                        warnings.push(AppError::new(AppErrorKind::NotFound, format!("Image {} not found for {}/{}", image_name, object_type, animation_name))
+                            .with_file(&image_path));
+                    }
+                }
+            }
+        }
+    }
+    if let Some(subfolders) = animations.get_mut("subfolders").and_then(|s| s.as_array_mut()) {
+        for subfolder in subfolders {
+            resolve_animation_frames(subfolder, object_type, images_dir, frames, warnings);
+        }
+    }
+}
+
+fn index_object_type(project_dir: &Path, name: String, path: String, warnings: &mut Vec<AppError>) -> ObjectTypeIndexEntry {
+    let src = project_dir.join("objectTypes").join(&path).join(format!("{}.json", name));
+    let images_dir = project_dir.join("images");
+    let mut frames = Vec::new();
+    let (properties, original_json) = match read_json(&src, warnings) {
+        Some((mut properties, original_json)) => {
+            if let Some(animations) = properties.get_mut("animations") {
+                resolve_animation_frames(animations, &name, &images_dir, &mut frames, warnings);
+            }
+            if let Some(image) = properties.get_mut("image").and_then(|i| i.as_object_mut()) {
+                let image_path = images_dir.join(format!("{}.png", name.to_lowercase()));
+                if image_path.exists() {
+                    image.insert("src".to_string(), Value::String(image_path.to_string_lossy().into_owned()));
+                } else {
+                    warnings.push(AppError::new(AppErrorKind::NotFound, format!("Image {}.png not found for {}", name.to_lowercase(), name))
+                        .with_file(&image_path));
+                }
+            }
+            (Some(properties), Some(original_json))
+        }
+        None => (None, None),
+    };
+    ObjectTypeIndexEntry { name, path, src: src.to_string_lossy().into_owned(), properties, original_json, frames }
+}
+
+fn index_family(project_dir: &Path, name: String, path: String, warnings: &mut Vec<AppError>) -> FamilyIndexEntry {
+    let src = project_dir.join("families").join(&path).join(format!("{}.json", name));
+    let (properties, original_json) = match read_json(&src, warnings) {
+        Some((properties, original_json)) => (Some(properties), Some(original_json)),
+        None => (None, None),
+    };
+    FamilyIndexEntry { name, path, src: src.to_string_lossy().into_owned(), properties, original_json }
+}
+
+fn index_layout(project_dir: &Path, name: String, path: String, warnings: &mut Vec<AppError>) -> LayoutIndexEntry {
+    let mut layer_count = 0;
+    let mut instance_count = 0;
+    if let Some((layout_data, _)) = read_json(&project_dir.join(&path), warnings) {
+        if let Some(layers) = layout_data.get("layers").and_then(|l| l.as_array()) {
+            layer_count = layers.len();
+            instance_count = layers.iter()
+                .filter_map(|layer| layer.get("instances").and_then(|i| i.as_array()))
+                .map(|instances| instances.len())
+                .sum();
+        }
+    }
+    LayoutIndexEntry { name, path, layer_count, instance_count }
+}
+
+fn run_job(project_dir: &Path, job: IndexJob) -> (IndexedFile, Vec<AppError>) {
+    let mut warnings = Vec::new();
+    let indexed = match job {
+        IndexJob::ObjectType { name, path } => IndexedFile::ObjectType(index_object_type(project_dir, name, path, &mut warnings)),
+        IndexJob::Family { name, path } => IndexedFile::Family(index_family(project_dir, name, path, &mut warnings)),
+        IndexJob::Layout { name, path } => IndexedFile::Layout(index_layout(project_dir, name, path, &mut warnings)),
+    };
+    (indexed, warnings)
+}
+
+pub async fn build_index(project_dir: PathBuf, mut on_progress: impl FnMut(f64, &str)) -> Result<ProjectIndex, AppError> {
+    let project = Project::load(&project_dir)?;
+    let mut jobs = folder_jobs(&project.object_types, |name, path| IndexJob::ObjectType { name, path });
+    jobs.extend(folder_jobs(&project.families, |name, path| IndexJob::Family { name, path }));
+    jobs.extend(project.layout_entries.iter().map(|l| IndexJob::Layout { name: l.name.clone(), path: l.path.clone() }));
+
+    let total = jobs.len();
+    let mut set = JoinSet::new();
+    for (order, job) in jobs.into_iter().enumerate() {
+        let project_dir = project_dir.clone();
+        set.spawn_blocking(move || {
+            let label = job.label();
+            let (indexed, warnings) = run_job(&project_dir, job);
+            (order, label, indexed, warnings)
+        });
+    }
+
+    let mut results = Vec::with_capacity(total);
+    while let Some(joined) = set.join_next().await {
+        let (order, label, indexed, file_warnings) = joined?;
+        on_progress((results.len() + 1) as f64 / total.max(1) as f64, &label);
+        results.push((order, indexed, file_warnings));
+    }
+    // Files finish in any order; hand them back in c3proj tree order.
+    results.sort_by_key(|(order, _, _)| *order);
+
+    let mut index = ProjectIndex { project, object_types: Vec::new(), families: Vec::new(), layouts: Vec::new(), warnings: Vec::new() };
+    for (_, indexed, mut file_warnings) in results {
+        index.warnings.append(&mut file_warnings);
+        match indexed {
+            IndexedFile::ObjectType(entry) => index.object_types.push(entry),
+            IndexedFile::Family(entry) => index.families.push(entry),
+            IndexedFile::Layout(entry) => index.layouts.push(entry),
+        }
+    }
+    Ok(index)
+}
+
+#[tauri::command]
+pub async fn index_project(window: tauri::Window, project_path: String) -> Result<ProjectIndex, AppError> {
+    window.emit("progress", Payload { progress: 0.0, filename: "".into(), done: false, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+    let index = build_index(PathBuf::from(project_path), |progress, filename| {
+        window.emit("progress", Payload { progress, filename: filename.into(), done: false, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+    }).await?;
+    window.emit("progress", Payload { progress: 1.0, filename: "".into(), done: true, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+    Ok(index)
+}
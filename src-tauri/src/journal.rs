@@ -0,0 +1,291 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppErrorKind};
+use crate::snapshot::{app_data_dir, content_hash, create_snapshot, project_key, relative_project_path};
+
+/// Oldest operations are dropped past this point.
+const MAX_OPERATIONS: usize = 200;
+
+const SOURCE_FILE: &str = "source.txt";
+
+// Commands run on a thread pool, the journal is a read-modify-write of a single file.
+static JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalFile {
+    path: String,
+    /// Content hashes, `None` when the file didn't exist on that side of the operation.
+    before: Option<String>,
+    after: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalOperation {
+    id: u64,
+    command: String,
+    created_at: u64,
+    files: Vec<JournalFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Journal {
+    operations: Vec<JournalOperation>,
+    /// Number of operations currently applied: `operations[..position]` can be undone, the rest redone.
+    position: usize,
+    next_id: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct JournalState {
+    operations: Vec<JournalOperation>,
+    position: usize,
+}
+
+fn journal_dir(data_dir: &Path, project_dir: &Path) -> PathBuf {
+    data_dir.join("journal").join(project_key(project_dir))
+}
+
+fn load(dir: &Path) -> Result<Journal, AppError> {
+    let path = dir.join("journal.json");
+    if !path.exists() {
+        return Ok(Journal::default());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| AppError::io(&path, e))?;
+    serde_json::from_str(&content).map_err(|e| AppError::json(&path, e))
+}
+
+fn save(dir: &Path, journal: &Journal) -> Result<(), AppError> {
+    fs::create_dir_all(dir).map_err(|e| AppError::io(dir, e))?;
+    let path = dir.join("journal.json");
+    let content = serde_json::to_string_pretty(journal).map_err(|e| AppError::json(&path, e))?;
+    fs::write(&path, content).map_err(|e| AppError::io(&path, e))
+}
+
+fn store_blob(dir: &Path, content: &[u8]) -> Result<String, AppError> {
+    let hash = content_hash(content);
+    let blobs_dir = dir.join("blobs");
+    let blob_path = blobs_dir.join(&hash);
+    if !blob_path.exists() {
+        fs::create_dir_all(&blobs_dir).map_err(|e| AppError::io(&blobs_dir, e))?;
+        fs::write(&blob_path, content).map_err(|e| AppError::io(&blob_path, e))?;
+    }
+    Ok(hash)
+}
+
+fn read_blob(dir: &Path, hash: &str) -> Result<Vec<u8>, AppError> {
+    let blob_path = dir.join("blobs").join(hash);
+    fs::read(&blob_path).map_err(|e| AppError::io(&blob_path, e))
+}
+
+/// Removes blobs no operation refers to anymore.
+fn collect_garbage(dir: &Path, journal: &Journal) {
+    let referenced: HashSet<&String> = journal.operations.iter()
+        .flat_map(|op| op.files.iter())
+        .flat_map(|f| f.before.iter().chain(f.after.iter()))
+        .collect();
+    if let Ok(entries) = fs::read_dir(dir.join("blobs")) {
+        for entry in entries.flatten() {
+            if !referenced.contains(&entry.file_name().to_string_lossy().into_owned()) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+/// Records one operation. `writes` holds the new content of every file, which must not be written yet.
+pub fn record(data_dir: &Path, project_dir: &Path, command: &str, writes: &[(PathBuf, Vec<u8>)]) -> Result<(), AppError> {
+    let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = journal_dir(data_dir, project_dir);
+    let mut journal = load(&dir)?;
+
+    let mut files = Vec::new();
+    for (path, content) in writes {
+        let before = match fs::read(path) {
+            Ok(current) => Some(store_blob(&dir, &current)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(AppError::io(path, e)),
+        };
+        let after = Some(store_blob(&dir, content)?);
+        files.push(JournalFile { path: relative_project_path(project_dir, path)?, before, after });
+    }
+
+    // A new operation replaces whatever could have been redone.
+    journal.operations.truncate(journal.position);
+    journal.operations.push(JournalOperation {
+        id: journal.next_id,
+        command: command.to_string(),
+        created_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+        files,
+    });
+    journal.next_id += 1;
+    if journal.operations.len() > MAX_OPERATIONS {
+        let excess = journal.operations.len() - MAX_OPERATIONS;
+        journal.operations.drain(..excess);
+    }
+    journal.position = journal.operations.len();
+    save(&dir, &journal)?;
+    collect_garbage(&dir, &journal);
+    Ok(())
+}
+
+/// Puts every file of `operation` back to its `before` content (undo) or `after` content (redo),
+/// refusing if any of them no longer matches the other side (it was edited outside the app since).
+fn apply_side(
+    data_dir: &Path,
+    project_dir: &Path,
+    dir: &Path,
+    operation: &JournalOperation,
+    undo: bool,
+) -> Result<(), AppError> {
+    let mut conflicts = Vec::new();
+    for file in &operation.files {
+        let expected = if undo { &file.after } else { &file.before };
+        let path = project_dir.join(&file.path);
+        let matches = match (fs::read(&path), expected) {
+            (Ok(current), Some(hash)) => current == read_blob(dir, hash)?,
+            (Err(e), None) if e.kind() == std::io::ErrorKind::NotFound => true,
+            (Err(e), Some(_)) if e.kind() != std::io::ErrorKind::NotFound => return Err(AppError::io(&path, e)),
+            _ => false,
+        };
+        if !matches {
+            conflicts.push(file.path.clone());
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(AppError::new(
+            AppErrorKind::Conflict,
+            format!("{} file(s) changed outside the app since '{}': {}", conflicts.len(), operation.command, conflicts.join(", ")),
+        ).with_file(project_dir.join(&conflicts[0])));
+    }
+
+    let targets: Vec<PathBuf> = operation.files.iter().map(|f| project_dir.join(&f.path)).collect();
+    create_snapshot(data_dir, project_dir, if undo { "undo" } else { "redo" }, &targets)?;
+    for (file, path) in operation.files.iter().zip(targets) {
+        let wanted = if undo { &file.before } else { &file.after };
+        match wanted {
+            Some(hash) => {
+                let content = read_blob(dir, hash)?;
+                fs::write(&path, content).map_err(|e| AppError::io(&path, e))?;
+            }
+            None => {
+                if path.exists() {
+                    fs::remove_file(&path).map_err(|e| AppError::io(&path, e))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn undo(data_dir: &Path, project_dir: &Path) -> Result<JournalOperation, AppError> {
+    let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = journal_dir(data_dir, project_dir);
+    let mut journal = load(&dir)?;
+    if journal.position == 0 {
+        return Err(AppError::new(AppErrorKind::NotFound, "Nothing to undo"));
+    }
+    let operation = journal.operations[journal.position - 1].clone();
+    apply_side(data_dir, project_dir, &dir, &operation, true)?;
+    journal.position -= 1;
+    save(&dir, &journal)?;
+    Ok(operation)
+}
+
+pub fn redo(data_dir: &Path, project_dir: &Path) -> Result<JournalOperation, AppError> {
+    let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = journal_dir(data_dir, project_dir);
+    let mut journal = load(&dir)?;
+    if journal.position >= journal.operations.len() {
+        return Err(AppError::new(AppErrorKind::NotFound, "Nothing to redo"));
+    }
+    let operation = journal.operations[journal.position].clone();
+    apply_side(data_dir, project_dir, &dir, &operation, false)?;
+    journal.position += 1;
+    save(&dir, &journal)?;
+    Ok(operation)
+}
+
+/// Whether the project has no operations to undo or redo.
+pub fn is_empty(data_dir: &Path, project_dir: &Path) -> Result<bool, AppError> {
+    let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(load(&journal_dir(data_dir, project_dir))?.operations.is_empty())
+}
+
+/// Hash of the archive the project folder was extracted from, as recorded by `set_source_hash`.
+pub fn source_hash(data_dir: &Path, project_dir: &Path) -> Option<String> {
+    fs::read_to_string(journal_dir(data_dir, project_dir).join(SOURCE_FILE)).ok()
+}
+
+pub fn set_source_hash(data_dir: &Path, project_dir: &Path, hash: &str) -> Result<(), AppError> {
+    let dir = journal_dir(data_dir, project_dir);
+    fs::create_dir_all(&dir).map_err(|e| AppError::io(&dir, e))?;
+    let path = dir.join(SOURCE_FILE);
+    fs::write(&path, hash).map_err(|e| AppError::io(&path, e))
+}
+
+/// Forgets every operation of a project, for a folder whose files got replaced from outside of the app.
+pub fn clear(data_dir: &Path, project_dir: &Path) -> Result<(), AppError> {
+    let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = journal_dir(data_dir, project_dir);
+    match fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AppError::io(&dir, e)),
+        _ => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub fn get_journal(app: tauri::AppHandle, project_path: String) -> Result<JournalState, AppError> {
+    let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let journal = load(&journal_dir(&app_data_dir(&app)?, Path::new(&project_path)))?;
+    Ok(JournalState { operations: journal.operations, position: journal.position })
+}
+
+#[tauri::command]
+pub fn undo_operation(app: tauri::AppHandle, project_path: String) -> Result<JournalOperation, AppError> {
+    undo(&app_data_dir(&app)?, Path::new(&project_path))
+}
+
+#[tauri::command]
+pub fn redo_operation(app: tauri::AppHandle, project_path: String) -> Result<JournalOperation, AppError> {
+    redo(&app_data_dir(&app)?, Path::new(&project_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("construct-crawler-test-journal-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn store_blob_names_blobs_by_content() {
+        let dir = temp_dir("blobs");
+        let hash = store_blob(&dir, b"layout").unwrap();
+        assert_eq!(hash, "1dc5ae5b68174891b6aa9850aa05ee0d9ae8a20468d9517259951a2dd9e9c0f0");
+        assert_eq!(store_blob(&dir, b"layout").unwrap(), hash);
+        assert_ne!(store_blob(&dir, b"LAYOUT").unwrap(), hash);
+        assert_eq!(read_blob(&dir, &hash).unwrap(), b"layout");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn source_hash_survives_until_cleared() {
+        let data_dir = temp_dir("source");
+        let project_dir = data_dir.join("project");
+        assert_eq!(source_hash(&data_dir, &project_dir), None);
+        set_source_hash(&data_dir, &project_dir, "abc").unwrap();
+        assert_eq!(source_hash(&data_dir, &project_dir).as_deref(), Some("abc"));
+        assert!(is_empty(&data_dir, &project_dir).unwrap());
+        clear(&data_dir, &project_dir).unwrap();
+        assert_eq!(source_hash(&data_dir, &project_dir), None);
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -0,0 +1,616 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::changes::ChangeSet;
+use crate::error::{AppError, AppErrorKind};
+use crate::project::Project;
+use crate::templates::{instance_uid, layout_instances, layout_layers};
+use crate::uids::UidRegistry;
+use crate::{read_json_file, snapshot};
+
+/// A layout file. Only what the app works with is typed, everything else lands in `properties`.
+/// Edits are made on the raw JSON so the keys Construct wrote keep their order in the file.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct Layout {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub sid: u64,
+    #[serde(default)]
+    pub layers: Vec<Layer>,
+    #[serde(flatten)]
+    pub properties: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct Layer {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub sid: u64,
+    /// Bottom to top.
+    #[serde(default)]
+    pub instances: Vec<Instance>,
+    #[serde(default)]
+    pub sub_layers: Vec<Layer>,
+    #[serde(flatten)]
+    pub properties: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct Instance {
+    #[serde(default, rename(deserialize = "type"))]
+    pub object_type: String,
+    #[serde(default)]
+    pub uid: u32,
+    #[serde(default)]
+    pub world: Map<String, Value>,
+    #[serde(default)]
+    pub instance_variables: Map<String, Value>,
+    #[serde(default)]
+    pub behaviors: Map<String, Value>,
+    #[serde(default)]
+    pub template: Option<Value>,
+    #[serde(default)]
+    pub children: Vec<Value>,
+    #[serde(flatten)]
+    pub properties: Map<String, Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LayerPayload {
+    project_path: String,
+    layout_file_path: String,
+    layer_name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RenameLayerPayload {
+    project_path: String,
+    layout_file_path: String,
+    layer_name: String,
+    new_name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MoveLayerPayload {
+    project_path: String,
+    layout_file_path: String,
+    layer_name: String,
+    /// `None` to move the layer to the top level.
+    #[serde(default)]
+    parent_layer_name: Option<String>,
+    /// Position among its new siblings, 0 being the bottom. Clamped to the end.
+    index: usize,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DuplicateLayerPayload {
+    project_path: String,
+    layout_file_path: String,
+    layer_name: String,
+    /// Name of the copy, `<name> copy` when left out.
+    #[serde(default)]
+    new_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MoveInstancesPayload {
+    project_path: String,
+    layout_file_path: String,
+    instance_uids: Vec<u32>,
+    target_layer_name: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LayerEditResult {
+    message: String,
+    /// The layout as written.
+    layout: Layout,
+}
+
+fn parse_layout(layout_path: &Path, layout_data: &Value) -> Result<Layout, AppError> {
+    serde_json::from_value(layout_data.clone()).map_err(|e| AppError::json(layout_path, e))
+}
+
+/// Index path of the first layer named `name`, `[2, 0]` being the first sub-layer of the third layer.
+fn find_layer(layout_data: &Value, name: &str) -> Option<Vec<usize>> {
+    fn search(layers: &[Value], name: &str, path: &mut Vec<usize>) -> bool {
+        for (index, layer) in layers.iter().enumerate() {
+            path.push(index);
+            if layer.get("name").and_then(|n| n.as_str()) == Some(name) {
+                return true;
+            }
+            if let Some(sub_layers) = layer.get("subLayers").and_then(|l| l.as_array()) {
+                if search(sub_layers, name, path) {
+                    return true;
+                }
+            }
+            path.pop();
+        }
+        false
+    }
+    let mut path = Vec::new();
+    search(layout_data.get("layers")?.as_array()?, name, &mut path).then_some(path)
+}
+
+fn layer_pointer(path: &[usize]) -> String {
+    let indices: Vec<String> = path.iter().map(|i| i.to_string()).collect();
+    format!("/layers/{}", indices.join("/subLayers/"))
+}
+
+/// The layer list `path` points into: the top level list for an empty path, otherwise the sub-layers
+/// of that layer, which are created when it has none yet.
+fn layer_list_mut<'a>(layout_data: &'a mut Value, path: &[usize]) -> Option<&'a mut Vec<Value>> {
+    let mut list = layout_data.get_mut("layers")?.as_array_mut()?;
+    for index in path {
+        list = list.get_mut(*index)?
+            .as_object_mut()?
+            .entry("subLayers".to_string())
+            .or_insert_with(|| Value::Array(Vec::new()))
+            .as_array_mut()?;
+    }
+    Some(list)
+}
+
+fn layer_mut<'a>(layout_data: &'a mut Value, path: &[usize]) -> Option<&'a mut Map<String, Value>> {
+    let (index, parent_path) = path.split_last()?;
+    layer_list_mut(layout_data, parent_path)?.get_mut(*index)?.as_object_mut()
+}
+
+/// Lowercased names of every layer, Construct doesn't allow two layers whose names only differ by case.
+fn layer_names(layout_data: &Value) -> HashSet<String> {
+    layout_layers(layout_data).into_iter()
+        .filter_map(|(_, layer)| layer.get("name").and_then(|n| n.as_str()))
+        .map(|name| name.to_lowercase())
+        .collect()
+}
+
+fn unique_layer_name(base: &str, taken: &mut HashSet<String>) -> String {
+    let mut name = format!("{} copy", base);
+    let mut suffix = 1;
+    while taken.contains(&name.to_lowercase()) {
+        suffix += 1;
+        name = format!("{} copy {}", base, suffix);
+    }
+    taken.insert(name.to_lowercase());
+    name
+}
+
+fn collect_sids(value: &Value, sids: &mut HashSet<u64>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(sid) = map.get("sid").and_then(|s| s.as_u64()) {
+                sids.insert(sid);
+            }
+            map.values().for_each(|v| collect_sids(v, sids));
+        }
+        Value::Array(values) => values.iter().for_each(|v| collect_sids(v, sids)),
+        _ => {}
+    }
+}
+
+/// A SID that isn't in `used` yet, Construct's are 15 digit numbers.
+fn new_sid(used: &mut HashSet<u64>) -> u64 {
+    let mut seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+    loop {
+        seed = snapshot::fnv1a(&seed.to_le_bytes());
+        let sid = 100_000_000_000_000 + seed % 900_000_000_000_000;
+        if used.insert(sid) {
+            return sid;
+        }
+    }
+}
+
+/// Points `children` entries (a UID or an object with a `uid`) at the new UIDs, dropping the ones not in `new_uids`.
+fn remap_children(instance: &mut Map<String, Value>, new_uids: &HashMap<u32, u32>) {
+    if let Some(children) = instance.get_mut("children").and_then(|c| c.as_array_mut()) {
+        children.retain_mut(|child| {
+            let old_uid = child.as_u64().or_else(|| child.get("uid").and_then(|u| u.as_u64())).map(|u| u as u32);
+            let Some(new_uid) = old_uid.and_then(|uid| new_uids.get(&uid)) else { return false };
+            match child {
+                Value::Object(child_obj) => {
+                    child_obj.insert("uid".to_string(), Value::from(*new_uid));
+                }
+                _ => *child = Value::from(*new_uid),
+            }
+            true
+        });
+    }
+}
+
+/// Removes `children` entries pointing at `removed` UIDs.
+fn drop_children(instance: &mut Map<String, Value>, removed: &HashSet<u32>) {
+    if let Some(children) = instance.get_mut("children").and_then(|c| c.as_array_mut()) {
+        children.retain(|child| {
+            let uid = child.as_u64().or_else(|| child.get("uid").and_then(|u| u.as_u64()));
+            !uid.is_some_and(|uid| removed.contains(&(uid as u32)))
+        });
+    }
+}
+
+/// Gives the copied layer and its sub-layers new names and SIDs, and their instances new UIDs
+/// starting after `last_uid`. Returns the old to new UID map.
+fn renumber_copy(
+    layer: &mut Value,
+    taken_names: &mut HashSet<String>,
+    used_sids: &mut HashSet<u64>,
+    last_uid: &mut u32,
+    new_uids: &mut HashMap<u32, u32>,
+) {
+    let Some(layer_obj) = layer.as_object_mut() else { return };
+    if layer_obj.contains_key("sid") {
+        layer_obj.insert("sid".to_string(), Value::from(new_sid(used_sids)));
+    }
+    if let Some(instances) = layer_obj.get_mut("instances").and_then(|i| i.as_array_mut()) {
+        for instance in instances.iter_mut().filter_map(|i| i.as_object_mut()) {
+            *last_uid += 1;
+            new_uids.insert(instance_uid(instance), *last_uid);
+            instance.insert("uid".to_string(), Value::from(*last_uid));
+        }
+    }
+    if let Some(sub_layers) = layer_obj.get_mut("subLayers").and_then(|l| l.as_array_mut()) {
+        for sub_layer in sub_layers {
+            let sub_layer_name = sub_layer.get("name").and_then(|n| n.as_str()).unwrap_or_default().to_string();
+            if let Some(sub_layer_obj) = sub_layer.as_object_mut() {
+                sub_layer_obj.insert("name".to_string(), Value::String(unique_layer_name(&sub_layer_name, taken_names)));
+            }
+            renumber_copy(sub_layer, taken_names, used_sids, last_uid, new_uids);
+        }
+    }
+}
+
+fn for_each_instance_mut(layer: &mut Value, f: &mut impl FnMut(&mut Map<String, Value>)) {
+    if let Some(instances) = layer.get_mut("instances").and_then(|i| i.as_array_mut()) {
+        instances.iter_mut().filter_map(|i| i.as_object_mut()).for_each(&mut *f);
+    }
+    if let Some(sub_layers) = layer.get_mut("subLayers").and_then(|l| l.as_array_mut()) {
+        for sub_layer in sub_layers {
+            for_each_instance_mut(sub_layer, f);
+        }
+    }
+}
+
+/// Removes the instances listed in `uids` from `layers` and their sub-layers, in layout order.
+fn take_instances(layers: &mut [Value], uids: &HashSet<u32>, taken: &mut Vec<Value>) {
+    for layer in layers {
+        if let Some(instances) = layer.get_mut("instances").and_then(|i| i.as_array_mut()) {
+            let (moved, kept): (Vec<Value>, Vec<Value>) = instances.drain(..)
+                .partition(|i| i.as_object().is_some_and(|i| uids.contains(&instance_uid(i))));
+            *instances = kept;
+            taken.extend(moved);
+        }
+        if let Some(sub_layers) = layer.get_mut("subLayers").and_then(|l| l.as_array_mut()) {
+            take_instances(sub_layers, uids, taken);
+        }
+    }
+}
+
+struct LayoutEdit {
+    project_base_path: PathBuf,
+    layout_path: PathBuf,
+    layout_data: Value,
+}
+
+impl LayoutEdit {
+    fn open(project_path: &str, layout_file_path: &str) -> Result<LayoutEdit, AppError> {
+        let project_base_path = PathBuf::from(project_path);
+        let layout_path = project_base_path.join(layout_file_path);
+        let (layout_data, _) = read_json_file(&layout_path)?;
+        if layout_data.get("layers").and_then(|l| l.as_array()).is_none() {
+            return Err(AppError::new(AppErrorKind::InvalidStructure, "Could not find 'layers' array in layout file")
+                .with_file(&layout_path)
+                .at_pointer("/layers"));
+        }
+        Ok(LayoutEdit { project_base_path, layout_path, layout_data })
+    }
+
+    fn find_layer(&self, name: &str) -> Result<Vec<usize>, AppError> {
+        find_layer(&self.layout_data, name)
+            .ok_or_else(|| AppError::new(AppErrorKind::NotFound, format!("No layer named '{}'", name)).with_file(&self.layout_path))
+    }
+
+    fn layer_mut(&mut self, path: &[usize]) -> Result<&mut Map<String, Value>, AppError> {
+        let pointer = layer_pointer(path);
+        let layout_path = self.layout_path.clone();
+        layer_mut(&mut self.layout_data, path)
+            .ok_or_else(|| AppError::new(AppErrorKind::InvalidStructure, "Layer is not an object").with_file(layout_path).at_pointer(pointer))
+    }
+
+    fn layer_list_mut(&mut self, path: &[usize]) -> Result<&mut Vec<Value>, AppError> {
+        let layout_path = self.layout_path.clone();
+        layer_list_mut(&mut self.layout_data, path)
+            .ok_or_else(|| AppError::new(AppErrorKind::InvalidStructure, "Layer is not an object").with_file(layout_path).at_pointer(layer_pointer(path)))
+    }
+
+    /// Errors when `name` is empty or another layer than `except` already uses it.
+    fn check_new_name(&self, name: &str, except: Option<&str>) -> Result<(), AppError> {
+        if name.is_empty() {
+            return Err(AppError::new(AppErrorKind::InvalidInput, "The layer name is empty"));
+        }
+        let mut taken = layer_names(&self.layout_data);
+        if let Some(except) = except {
+            taken.remove(&except.to_lowercase());
+        }
+        if taken.contains(&name.to_lowercase()) {
+            return Err(AppError::new(AppErrorKind::InvalidInput, format!("The layout already has a layer named '{}'", name)).with_file(&self.layout_path));
+        }
+        Ok(())
+    }
+
+    /// Event sheets referring to the layer by name are left as they are.
+    fn rename_layer(&mut self, layer_name: &str, new_name: &str) -> Result<String, AppError> {
+        let new_name = new_name.trim();
+        let path = self.find_layer(layer_name)?;
+        self.check_new_name(new_name, Some(layer_name))?;
+        self.layer_mut(&path)?.insert("name".to_string(), Value::String(new_name.to_string()));
+        Ok(format!("Renamed layer '{}' to '{}'.", layer_name, new_name))
+    }
+
+    fn move_layer(&mut self, layer_name: &str, parent_layer_name: Option<&str>, index: usize) -> Result<String, AppError> {
+        let path = self.find_layer(layer_name)?;
+        if let Some(parent_name) = parent_layer_name {
+            if self.find_layer(parent_name)?.starts_with(&path) {
+                return Err(AppError::new(AppErrorKind::InvalidInput, format!("Layer '{}' can't be moved inside itself", layer_name)));
+            }
+        }
+        let (layer_index, parent_path) = path.split_last().ok_or_else(|| AppError::internal("Empty layer path"))?;
+        let layer = self.layer_list_mut(parent_path)?.remove(*layer_index);
+        // Removing the layer shifts the indices after it, so the new parent is looked up again.
+        let new_parent_path = match parent_layer_name {
+            Some(parent_name) => self.find_layer(parent_name)?,
+            None => Vec::new(),
+        };
+        let siblings = self.layer_list_mut(&new_parent_path)?;
+        siblings.insert(index.min(siblings.len()), layer);
+        Ok(match parent_layer_name {
+            Some(parent_name) => format!("Moved layer '{}' into '{}'.", layer_name, parent_name),
+            None => format!("Moved layer '{}'.", layer_name),
+        })
+    }
+
+    /// The copy goes right above the original. Its instances get new UIDs, copies of templates become
+    /// replicas of them, and hierarchy links pointing out of the copy are dropped.
+    fn duplicate_layer(&mut self, layer_name: &str, new_name: Option<&str>) -> Result<String, AppError> {
+        let path = self.find_layer(layer_name)?;
+        let mut taken_names = layer_names(&self.layout_data);
+        let new_name = match new_name.map(str::trim) {
+            Some(new_name) => {
+                self.check_new_name(new_name, None)?;
+                taken_names.insert(new_name.to_lowercase());
+                new_name.to_string()
+            }
+            None => unique_layer_name(layer_name, &mut taken_names),
+        };
+        let mut used_sids = HashSet::new();
+        collect_sids(&self.layout_data, &mut used_sids);
+        let project = Project::load(&self.project_base_path)?;
+        let mut last_uid = UidRegistry::scan_complete(&self.project_base_path, &project)?.max_uid();
+
+        let mut copy = Value::Object(self.layer_mut(&path)?.clone());
+        if let Some(copy_obj) = copy.as_object_mut() {
+            copy_obj.insert("name".to_string(), Value::String(new_name.clone()));
+        }
+        let mut new_uids = HashMap::new();
+        renumber_copy(&mut copy, &mut taken_names, &mut used_sids, &mut last_uid, &mut new_uids);
+        for_each_instance_mut(&mut copy, &mut |instance| {
+            remap_children(instance, &new_uids);
+            if let Some(template_obj) = instance.get_mut("template").and_then(|t| t.as_object_mut()) {
+                if template_obj.get("mode").and_then(|m| m.as_str()) == Some("template") {
+                    let template_name = template_obj.get("templateName").cloned().unwrap_or_else(|| Value::String(String::new()));
+                    template_obj.insert("mode".to_string(), Value::String("replica".to_string()));
+                    template_obj.insert("sourceTemplateName".to_string(), template_name);
+                    template_obj.insert("templateName".to_string(), Value::String(String::new()));
+                }
+            }
+        });
+
+        let (index, parent_path) = path.split_last().ok_or_else(|| AppError::internal("Empty layer path"))?;
+        self.layer_list_mut(parent_path)?.insert(index + 1, copy);
+        Ok(format!("Duplicated layer '{}' as '{}' with {} instances.", layer_name, new_name, new_uids.len()))
+    }
+
+    /// Deletes the layer, its sub-layers and their instances. Hierarchy links to the deleted instances are dropped.
+    fn delete_layer(&mut self, layer_name: &str) -> Result<String, AppError> {
+        let path = self.find_layer(layer_name)?;
+        let (index, parent_path) = path.split_last().ok_or_else(|| AppError::internal("Empty layer path"))?;
+        if parent_path.is_empty() && self.layer_list_mut(parent_path)?.len() == 1 {
+            return Err(AppError::new(AppErrorKind::InvalidInput, "A layout needs at least one layer"));
+        }
+        let removed = self.layer_list_mut(parent_path)?.remove(*index);
+        let removed_uids: HashSet<u32> = layout_instances(&serde_json::json!({ "layers": [removed] })).into_iter()
+            .map(|(_, instance)| instance_uid(instance))
+            .collect();
+        if let Some(layers) = self.layout_data.get_mut("layers").and_then(|l| l.as_array_mut()) {
+            for layer in layers {
+                for_each_instance_mut(layer, &mut |instance| drop_children(instance, &removed_uids));
+            }
+        }
+        Ok(format!("Deleted layer '{}' and its {} instances.", layer_name, removed_uids.len()))
+    }
+
+    /// Moves the instances on top of the target layer, keeping their order and UIDs.
+    fn move_instances_to_layer(&mut self, instance_uids: &[u32], target_layer_name: &str) -> Result<String, AppError> {
+        let target_path = self.find_layer(target_layer_name)?;
+        let found: HashSet<u32> = layout_instances(&self.layout_data).into_iter().map(|(_, i)| instance_uid(i)).collect();
+        let missing: Vec<String> = instance_uids.iter().filter(|uid| !found.contains(uid)).map(|uid| uid.to_string()).collect();
+        if !missing.is_empty() {
+            return Err(AppError::new(AppErrorKind::NotFound, format!("No instance with UID {}", missing.join(", "))).with_file(&self.layout_path));
+        }
+        // Instances already on the target layer stay where they are.
+        let target_layer = self.layer_mut(&target_path)?;
+        let already_there: HashSet<u32> = target_layer.get("instances").and_then(|i| i.as_array()).into_iter().flatten()
+            .filter_map(|i| i.as_object())
+            .map(instance_uid)
+            .collect();
+        let uids: HashSet<u32> = instance_uids.iter().copied().filter(|uid| !already_there.contains(uid)).collect();
+
+        let mut moved = Vec::new();
+        if let Some(layers) = self.layout_data.get_mut("layers").and_then(|l| l.as_array_mut()) {
+            take_instances(layers, &uids, &mut moved);
+        }
+        let moved_count = moved.len();
+        let target_layer = self.layer_mut(&target_path)?;
+        match target_layer.get_mut("instances").and_then(|i| i.as_array_mut()) {
+            Some(instances) => instances.extend(moved),
+            None => {
+                target_layer.insert("instances".to_string(), Value::Array(moved));
+            }
+        }
+        Ok(format!("Moved {} instances to layer '{}'.", moved_count, target_layer_name))
+    }
+
+    fn save(self, app: &tauri::AppHandle, command: &str, message: String) -> Result<LayerEditResult, AppError> {
+        let layout = parse_layout(&self.layout_path, &self.layout_data)?;
+        let mut changes = ChangeSet::new(&self.project_base_path, command);
+        changes.write_json(self.layout_path, &self.layout_data)?;
+        changes.commit(&snapshot::app_data_dir(app)?)?;
+        Ok(LayerEditResult { message, layout })
+    }
+}
+
+#[tauri::command]
+pub fn get_layout(project_path: String, layout_file_path: String) -> Result<Layout, AppError> {
+    let layout_path = PathBuf::from(&project_path).join(&layout_file_path);
+    let (layout_data, _) = read_json_file(&layout_path)?;
+    parse_layout(&layout_path, &layout_data)
+}
+
+#[tauri::command]
+pub fn rename_layer(app: tauri::AppHandle, payload: RenameLayerPayload) -> Result<LayerEditResult, AppError> {
+    let mut edit = LayoutEdit::open(&payload.project_path, &payload.layout_file_path)?;
+    let message = edit.rename_layer(&payload.layer_name, &payload.new_name)?;
+    edit.save(&app, "rename_layer", message)
+}
+
+#[tauri::command]
+pub fn move_layer(app: tauri::AppHandle, payload: MoveLayerPayload) -> Result<LayerEditResult, AppError> {
+    let mut edit = LayoutEdit::open(&payload.project_path, &payload.layout_file_path)?;
+    let message = edit.move_layer(&payload.layer_name, payload.parent_layer_name.as_deref(), payload.index)?;
+    edit.save(&app, "move_layer", message)
+}
+
+#[tauri::command]
+pub fn duplicate_layer(app: tauri::AppHandle, payload: DuplicateLayerPayload) -> Result<LayerEditResult, AppError> {
+    let mut edit = LayoutEdit::open(&payload.project_path, &payload.layout_file_path)?;
+    let message = edit.duplicate_layer(&payload.layer_name, payload.new_name.as_deref())?;
+    edit.save(&app, "duplicate_layer", message)
+}
+
+#[tauri::command]
+pub fn delete_layer(app: tauri::AppHandle, payload: LayerPayload) -> Result<LayerEditResult, AppError> {
+    let mut edit = LayoutEdit::open(&payload.project_path, &payload.layout_file_path)?;
+    let message = edit.delete_layer(&payload.layer_name)?;
+    edit.save(&app, "delete_layer", message)
+}
+
+#[tauri::command]
+pub fn move_instances_to_layer(app: tauri::AppHandle, payload: MoveInstancesPayload) -> Result<LayerEditResult, AppError> {
+    let mut edit = LayoutEdit::open(&payload.project_path, &payload.layout_file_path)?;
+    let message = edit.move_instances_to_layer(&payload.instance_uids, &payload.target_layer_name)?;
+    edit.save(&app, "move_instances_to_layer", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    /// A project with `Main` (the layout being edited) and `Other`, whose UIDs the copies must not reuse.
+    fn open_project(name: &str, main: Value) -> LayoutEdit {
+        let dir = std::env::temp_dir().join(format!("construct-crawler-test-layout-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("layouts")).unwrap();
+        fs::write(dir.join("project.c3proj"), json!({ "layouts": { "items": ["Main", "Other"] } }).to_string()).unwrap();
+        fs::write(dir.join("layouts/Main.json"), main.to_string()).unwrap();
+        let other = json!({ "layers": [{ "name": "Layer 0", "instances": [{ "type": "Enemy", "uid": 50 }] }] });
+        fs::write(dir.join("layouts/Other.json"), other.to_string()).unwrap();
+        LayoutEdit::open(dir.to_str().unwrap(), "layouts/Main.json").unwrap()
+    }
+
+    fn main_layout() -> Value {
+        json!({ "layers": [
+            { "name": "Back", "sid": 100, "instances": [
+                { "type": "Player", "uid": 1, "template": { "mode": "template", "templateName": "main", "sourceTemplateName": "" }, "children": [2] },
+                { "type": "Gun", "uid": 2 }
+            ], "subLayers": [
+                { "name": "Detail", "sid": 101, "instances": [{ "type": "Sprite", "uid": 3, "children": [{ "uid": 9 }, { "uid": 2 }] }] }
+            ] },
+            { "name": "Front", "sid": 102, "instances": [{ "type": "Sprite", "uid": 9, "children": [1, { "uid": 3 }, 2] }] }
+        ] })
+    }
+
+    fn layer_names_in_order(edit: &LayoutEdit) -> Vec<String> {
+        layout_layers(&edit.layout_data).into_iter()
+            .map(|(_, layer)| layer["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn duplicate_layer_gives_the_copy_unique_uids_and_replicas() {
+        let mut edit = open_project("duplicate", main_layout());
+        edit.duplicate_layer("Back", None).unwrap();
+        assert_eq!(layer_names_in_order(&edit), ["Back", "Detail", "Back copy", "Detail copy", "Front"]);
+
+        fs::write(&edit.layout_path, edit.layout_data.to_string()).unwrap();
+        let project = Project::load(&edit.project_base_path).unwrap();
+        let registry = UidRegistry::scan_complete(&edit.project_base_path, &project).unwrap();
+        assert!(registry.duplicates().is_empty());
+        assert_eq!(registry.max_uid(), 53);
+
+        let copy = &edit.layout_data["layers"][1];
+        assert_ne!(copy["sid"], 100);
+        assert_eq!(copy["instances"][0]["uid"], 51);
+        assert_eq!(copy["instances"][0]["template"], json!({ "mode": "replica", "templateName": "", "sourceTemplateName": "main" }));
+        assert_eq!(copy["instances"][0]["children"], json!([52]));
+        // The link to 9 leads out of the copy and is dropped.
+        assert_eq!(copy["subLayers"][0]["instances"][0]["children"], json!([{ "uid": 52 }]));
+        assert_eq!(edit.layout_data["layers"][0], main_layout()["layers"][0]);
+        fs::remove_dir_all(&edit.project_base_path).unwrap();
+    }
+
+    #[test]
+    fn delete_layer_drops_children_pointing_at_removed_instances() {
+        let mut edit = open_project("delete", main_layout());
+        edit.delete_layer("Back").unwrap();
+        assert_eq!(layer_names_in_order(&edit), ["Front"]);
+        assert_eq!(edit.layout_data["layers"][0]["instances"][0]["children"], json!([]));
+        assert_eq!(edit.delete_layer("Front").unwrap_err().kind, AppErrorKind::InvalidInput);
+        fs::remove_dir_all(&edit.project_base_path).unwrap();
+    }
+
+    #[test]
+    fn rename_layer_refuses_a_name_already_in_use() {
+        let mut edit = open_project("rename", main_layout());
+        for taken in ["Front", "detail", " "] {
+            assert_eq!(edit.rename_layer("Back", taken).unwrap_err().kind, AppErrorKind::InvalidInput, "{}", taken);
+        }
+        edit.rename_layer("Back", "back").unwrap();
+        edit.rename_layer("Front", " Top ").unwrap();
+        assert_eq!(layer_names_in_order(&edit), ["back", "Detail", "Top"]);
+        fs::remove_dir_all(&edit.project_base_path).unwrap();
+    }
+
+    #[test]
+    fn move_layer_and_instances_keep_uids_and_order() {
+        let mut edit = open_project("move", main_layout());
+        assert_eq!(edit.move_layer("Back", Some("Detail"), 0).unwrap_err().kind, AppErrorKind::InvalidInput);
+        edit.move_layer("Front", Some("Back"), 0).unwrap();
+        assert_eq!(layer_names_in_order(&edit), ["Back", "Front", "Detail"]);
+
+        assert_eq!(edit.move_instances_to_layer(&[2, 42], "Detail").unwrap_err().kind, AppErrorKind::NotFound);
+        edit.move_instances_to_layer(&[9, 2, 3], "Detail").unwrap();
+        let uids = |layer: &Value| layer["instances"].as_array().unwrap().iter().map(|i| i["uid"].as_u64().unwrap()).collect::<Vec<_>>();
+        let back = &edit.layout_data["layers"][0];
+        assert_eq!(uids(back), [1]);
+        assert_eq!(uids(&back["subLayers"][0]), Vec::<u64>::new());
+        assert_eq!(uids(&back["subLayers"][1]), [3, 2, 9]);
+        fs::remove_dir_all(&edit.project_base_path).unwrap();
+    }
+}
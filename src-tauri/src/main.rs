@@ -4,71 +4,60 @@
 
 use std::fs::{self, File};
 use std::io::{Read, Cursor, Write};
-use std::fmt;
 use std::path::{Path, PathBuf};
 use byteorder::{BigEndian, ReadBytesExt};
+use chrono::{Datelike, Timelike};
 use zip::ZipArchive;
-use zip::result::ZipError;
-use std::io::{Error as IoError, ErrorKind};
 use tokio;
 use walkdir::WalkDir;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use quick_xml::events::Event;
 use quick_xml::reader::Reader;
-use std::collections::HashSet;
-
-// --- Error Handling ---
-#[derive(Debug)]
-#[allow(dead_code)]
-enum MyError {
-    IoError(IoError),
-    ZipError(ZipError),
-    WalkDirError(walkdir::Error),
-    XmlError(quick_xml::Error),
-    SerdeJsonError(serde_json::Error),
-    Custom(String),
-}
-
-impl fmt::Display for MyError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            MyError::IoError(e) => write!(f, "IO Error: {}", e),
-            MyError::ZipError(e) => write!(f, "ZIP Error: {}", e),
-            MyError::WalkDirError(e) => write!(f, "WalkDir Error: {}", e),
-            MyError::XmlError(e) => write!(f, "XML Error: {}", e),
-            MyError::SerdeJsonError(e) => write!(f, "JSON Error: {}", e),
-            MyError::Custom(s) => write!(f, "Error: {}", s),
-        }
-    }
-}
+use std::collections::{HashMap, HashSet};
 
-impl std::error::Error for MyError {}
-impl From<std::io::Error> for MyError { fn from(e: std::io::Error) -> Self { MyError::IoError(e) } }
-impl From<zip::result::ZipError> for MyError { fn from(e: zip::result::ZipError) -> Self { MyError::ZipError(e) } }
-impl From<walkdir::Error> for MyError { fn from(e: walkdir::Error) -> Self { MyError::WalkDirError(e) } }
-impl From<quick_xml::Error> for MyError { fn from(e: quick_xml::Error) -> Self { MyError::XmlError(e) } }
-impl From<serde_json::Error> for MyError { fn from(e: serde_json::Error) -> Self { MyError::SerdeJsonError(e) } }
+mod bulk_edit;
+mod changes;
+mod error;
+mod images;
+mod indexer;
+mod journal;
+mod layout;
+mod object_types;
+mod polygon;
+mod project;
+mod snapshot;
+mod templates;
+mod uids;
 
-impl From<MyError> for tauri::Error {
-    fn from(error: MyError) -> Self {
-        match error {
-            MyError::IoError(e) => tauri::Error::Io(e),
-            MyError::ZipError(e) => tauri::Error::Io(IoError::new(ErrorKind::Other, e.to_string())),
-            MyError::WalkDirError(e) => tauri::Error::Io(IoError::new(ErrorKind::Other, e.to_string())),
-            MyError::XmlError(e) => tauri::Error::Io(IoError::new(ErrorKind::Other, e.to_string())),
-            MyError::SerdeJsonError(e) => tauri::Error::Io(IoError::new(ErrorKind::Other, e.to_string())),
-            MyError::Custom(s) => tauri::Error::Io(IoError::new(ErrorKind::Other, s)),
-        }
+use changes::{json_diff, ChangeSet, JsonChange};
+use error::{AppError, AppErrorKind, WithWarnings};
+
+fn read_json_file(path: &Path) -> Result<(Value, String), AppError> {
+    if !path.exists() {
+        return Err(AppError::not_found(path));
     }
+    let content = fs::read_to_string(path).map_err(|e| AppError::io(path, e))?;
+    let data = serde_json::from_str(&content).map_err(|e| AppError::json(path, e))?;
+    Ok((data, content))
 }
-// --- End Error Handling ---
 
-#[derive(Clone, serde::Serialize)]
+#[derive(Clone, Default, serde::Serialize)]
 struct Payload {
   progress: f64,
   filename: String,
   done: bool,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  rejected_entries: Vec<RejectedEntry>,
+  /// Set when opening a different archive dropped the undo history and snapshots of the previous one.
+  #[serde(skip_serializing_if = "std::ops::Not::not")]
+  history_cleared: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RejectedEntry {
+    name: String,
+    reason: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -86,6 +75,22 @@ pub struct InstanceDisplayInfo {
     template_or_source_name: Option<String>,
     x: f64,
     y: f64,
+    width: f64,
+    height: f64,
+    /// In radians, as stored in the layout file.
+    angle: f64,
+    opacity: f64,
+    layer_name: String,
+    /// Position of the layer in Construct's layer list, sub-layers included.
+    layer_index: usize,
+    /// Position of the instance within its layer, 0 being the bottom.
+    z_index: usize,
+    instance_variables: serde_json::Map<String, Value>,
+    /// Behavior name to the properties set on this instance.
+    behaviors: serde_json::Map<String, Value>,
+    /// Hierarchy links, children are listed in their scene graph order.
+    parent_uid: Option<u32>,
+    children_uids: Vec<u32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
@@ -102,6 +107,9 @@ pub struct SetReplicasPayload {
     layout_file_path: String,
     instance_uids: Vec<u32>,
     target_template_name: String,
+    /// Report what would change without writing anything.
+    #[serde(default)]
+    dry_run: bool,
 }
 
 #[derive(Deserialize, Debug)]
@@ -110,51 +118,126 @@ pub struct SetAllInstancesOfTypePayload {
     layout_file_paths: Vec<String>, // JS will send all layout paths
     object_type_name: String,
     target_template_name: String,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReplicaChange {
+    uid: u32,
+    /// Template mode before the change, `None` when the instance had no template data.
+    current_mode: Option<String>,
+    template_diff: Vec<JsonChange>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LayoutReplicaChanges {
+    layout_path: String,
+    instances: Vec<ReplicaChange>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RejectedInstance {
+    layout_path: String,
+    uid: u32,
+    reason: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReplicaConversionResult {
+    dry_run: bool,
+    message: String,
+    layouts: Vec<LayoutReplicaChanges>,
+    /// Selected instances that were left untouched, and why.
+    rejected: Vec<RejectedInstance>,
 }
 
 
 #[tauri::command]
-fn read_png_dimensions(file_path: String) -> Result<(u32, u32), String> {
-  let mut file = File::open(file_path).map_err(|e| e.to_string())?;
+fn read_png_dimensions(file_path: String) -> Result<(u32, u32), AppError> {
+  let mut file = File::open(&file_path).map_err(|e| AppError::io(&file_path, e))?;
   let mut buffer = vec![0; 24];
-  file.read_exact(&mut buffer).map_err(|e| e.to_string())?;
+  file.read_exact(&mut buffer).map_err(|e| AppError::io(&file_path, e))?;
   let mut cursor = Cursor::new(buffer);
   cursor.set_position(16);
-  let width = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
-  let height = cursor.read_u32::<BigEndian>().map_err(|e| e.to_string())?;
+  let width = cursor.read_u32::<BigEndian>().map_err(|e| AppError::io(&file_path, e))?;
+  let height = cursor.read_u32::<BigEndian>().map_err(|e| AppError::io(&file_path, e))?;
   Ok((width, height))
 }
 
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+fn is_symlink_entry(file: &zip::read::ZipFile) -> bool {
+    file.unix_mode().is_some_and(|mode| mode & S_IFMT == S_IFLNK)
+}
+
+fn extract_archive<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    dest_path: &Path,
+    mut on_progress: impl FnMut(f64, &str),
+) -> Result<Vec<RejectedEntry>, AppError> {
+    let total_files = archive.len();
+    let mut rejected = Vec::new();
+    for i in 0..total_files {
+        let mut file = archive.by_index(i)?;
+        let relative = match file.enclosed_name() {
+            Some(p) => p.to_owned(),
+            None => {
+                rejected.push(RejectedEntry { name: file.name().into(), reason: "Path is absolute or escapes the destination folder".into() });
+                continue;
+            }
+        };
+        if is_symlink_entry(&file) {
+            rejected.push(RejectedEntry { name: file.name().into(), reason: "Symbolic links are not allowed".into() });
+            continue;
+        }
+        let outpath = dest_path.join(&relative);
+        if file.is_dir() {
+            fs::create_dir_all(&outpath)?;
+        } else {
+            if let Some(p) = outpath.parent() {
+                if !p.exists() { fs::create_dir_all(p)?; }
+            }
+            let mut outfile = File::create(&outpath)?;
+            std::io::copy(&mut file, &mut outfile)?;
+            on_progress((i + 1) as f64 / total_files as f64, file.name());
+        }
+    }
+    Ok(rejected)
+}
+
 #[tauri::command]
-async fn extract_zip(window: tauri::Window, zip_path: String, dest_path: String) -> Result<(), String> {
+async fn extract_zip(app: tauri::AppHandle, window: tauri::Window, zip_path: String, dest_path: String) -> Result<(), AppError> {
+    let data_dir = snapshot::app_data_dir(&app)?;
+    let file_for_errors = zip_path.clone();
     let task = tokio::spawn(async move {
+        let project_dir = Path::new(&dest_path);
+        // Every .c3p is extracted to the same folder: history kept for another archive would undo into this one.
+        // Reopening the same archive keeps it, the extracted files are the ones it was recorded against.
+        let source_hash = snapshot::file_hash(Path::new(&zip_path))?;
+        let mut history_cleared = false;
+        if journal::source_hash(&data_dir, project_dir).as_deref() != Some(source_hash.as_str()) {
+            history_cleared = !journal::is_empty(&data_dir, project_dir)? || !snapshot::read_snapshots(&data_dir, project_dir)?.is_empty();
+            journal::clear(&data_dir, project_dir)?;
+            snapshot::clear(&data_dir, project_dir)?;
+        }
         let reader = File::open(&zip_path)?;
         let mut archive = ZipArchive::new(reader)?;
-        let total_files = archive.len();
-        window.emit("progress", Payload { progress: 0.0, filename: "".into(), done: false }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
-        for i in 0..total_files {
-            let mut file = archive.by_index(i)?;
-            let outpath = Path::new(&dest_path).join(file.name());
-            if file.is_dir() {
-                fs::create_dir_all(&outpath)?;
-            } else {
-                if let Some(p) = outpath.parent() {
-                    if !p.exists() { fs::create_dir_all(&p)?; }
-                }
-                let mut outfile = File::create(&outpath)?;
-                std::io::copy(&mut file, &mut outfile)?;
-                let progress = (i + 1) as f64 / total_files as f64;
-                window.emit("progress", Payload { progress, filename: file.name().into(), done: false }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
-            }
-        }
-        window.emit("progress", Payload { progress: 1.0, filename: "".into(), done: true }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
-        Ok::<(), MyError>(())
+        window.emit("progress", Payload { progress: 0.0, filename: "".into(), done: false, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+        let rejected_entries = extract_archive(&mut archive, project_dir, |progress, filename| {
+            window.emit("progress", Payload { progress, filename: filename.into(), done: false, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+        })?;
+        journal::set_source_hash(&data_dir, project_dir, &source_hash)?;
+        window.emit("progress", Payload { progress: 1.0, filename: "".into(), done: true, rejected_entries, history_cleared }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+        Ok::<(), AppError>(())
     });
-    task.await.map_err(|e| e.to_string())?.map_err(|e: MyError| e.to_string())
+    task.await?.map_err(|e: AppError| e.with_file(&file_for_errors))
 }
 
 #[tauri::command]
-async fn save_zip(window: tauri::Window, dir_path: String, zip_path: String) -> Result<(), String> {
+async fn save_zip(window: tauri::Window, dir_path: String, zip_path: String) -> Result<(), AppError> {
+    let file_for_errors = zip_path.clone();
     let task = tokio::spawn(async move {
         let zip_file = File::create(&zip_path)?;
         let total_files = WalkDir::new(&dir_path).into_iter().filter_map(Result::ok).count();
@@ -171,8 +254,7 @@ async fn save_zip(window: tauri::Window, dir_path: String, zip_path: String) ->
             let path = entry.path();
             let name = path.strip_prefix(Path::new(&dir_path)).unwrap();
             if path.is_file() {
-                let ext = path.extension().and_then(std::ffi::OsStr::to_str).unwrap_or("");
-                options = options.compression_method(if ext == "webm" { zip::CompressionMethod::Stored } else { zip::CompressionMethod::Deflated });
+                options = options.compression_method(compression_for_path(path));
                 zip.start_file(name.to_string_lossy().into_owned(), options)?;
                 let mut f = File::open(path)?;
                 f.read_to_end(&mut buffer)?;
@@ -180,23 +262,207 @@ async fn save_zip(window: tauri::Window, dir_path: String, zip_path: String) ->
                 buffer.clear();
                 processed_files += 1;
                 let progress = processed_files as f64 / total_files as f64;
-                window.emit("progress", Payload { progress, filename: name.to_string_lossy().into(), done: false }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+                window.emit("progress", Payload { progress, filename: name.to_string_lossy().into(), done: false, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
             } else if name.as_os_str().len() != 0 {
                 zip.add_directory(name.to_string_lossy().into_owned(), options)?;
             }
         }
         zip.finish()?;
-        window.emit("progress", Payload { progress: 1.0, filename: "".into(), done: true }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
-        Ok::<(), MyError>(())
+        window.emit("progress", Payload { progress: 1.0, filename: "".into(), done: true, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+        Ok::<(), AppError>(())
     });
-    task.await.map_err(|e| e.to_string())?.map_err(|e: MyError| e.to_string())
+    task.await?.map_err(|e: AppError| e.with_file(&file_for_errors))
+}
+
+fn compression_for_path(path: &Path) -> zip::CompressionMethod {
+    let ext = path.extension().and_then(std::ffi::OsStr::to_str).unwrap_or("");
+    if ext == "webm" { zip::CompressionMethod::Stored } else { zip::CompressionMethod::Deflated }
+}
+
+fn zip_entry_name(relative: &Path) -> String {
+    relative.components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+struct WriteBackEntry {
+    name: String,
+    is_dir: bool,
+    options: zip::write::FileOptions,
+}
+
+/// Zip timestamp of a file time. Zip stores local time, like the unzip tools show it.
+/// `None` outside of what zip can store (1980 to 2107).
+fn zip_date_time(time: std::time::SystemTime) -> Option<zip::DateTime> {
+    let local = chrono::DateTime::<chrono::Local>::from(time);
+    zip::DateTime::from_date_and_time(
+        u16::try_from(local.year()).ok()?, local.month() as u8, local.day() as u8,
+        local.hour() as u8, local.minute() as u8, local.second() as u8,
+    ).ok()
+}
+
+/// Fills `buffer` unless the reader ends first, returns how much was read.
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
+
+fn same_content(mut a: impl Read, mut b: impl Read) -> std::io::Result<bool> {
+    let (mut chunk_a, mut chunk_b) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    loop {
+        let (read_a, read_b) = (read_full(&mut a, &mut chunk_a)?, read_full(&mut b, &mut chunk_b)?);
+        if chunk_a[..read_a] != chunk_b[..read_b] {
+            return Ok(false);
+        }
+        if read_a == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+fn plan_write_back<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>, dir_path: &Path) -> Result<Vec<WriteBackEntry>, AppError> {
+    let mut planned = Vec::new();
+    let mut known_names = HashSet::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        let name = entry.name().to_string();
+        // Unsafe entries were never extracted, so there is nothing on disk to write back.
+        let on_disk = match entry.enclosed_name() {
+            Some(p) => dir_path.join(p),
+            None => continue,
+        };
+        // Parent folders are created on extraction even when the archive has no entry for them.
+        let mut known = name.trim_end_matches('/');
+        while known_names.insert(known.to_string()) {
+            let Some((parent, _)) = known.rsplit_once('/') else { break };
+            known = parent;
+        }
+        let (is_dir, size, last_modified) = (entry.is_dir(), entry.size(), entry.last_modified());
+        let options = zip::write::FileOptions::default()
+            .compression_method(entry.compression())
+            .unix_permissions(entry.unix_mode().unwrap_or(0o755))
+            .large_file(true);
+        drop(entry);
+        // Entries deleted from the working folder are dropped from the rewritten archive.
+        if is_dir {
+            if on_disk.is_dir() { planned.push(WriteBackEntry { name, is_dir: true, options: options.last_modified_time(last_modified) }); }
+        } else if on_disk.is_file() {
+            // Untouched files keep their time, edited ones get the time they were saved at.
+            let metadata = fs::metadata(&on_disk)?;
+            let unchanged = metadata.len() == size && same_content(archive.by_index(i)?, File::open(&on_disk)?)?;
+            let modified = if unchanged { None } else { metadata.modified().ok().and_then(zip_date_time) };
+            planned.push(WriteBackEntry { name, is_dir: false, options: options.last_modified_time(modified.unwrap_or(last_modified)) });
+        }
+    }
+    for entry_result in WalkDir::new(dir_path).sort_by_file_name() {
+        let entry = entry_result?;
+        let relative = entry.path().strip_prefix(dir_path).unwrap();
+        if relative.as_os_str().is_empty() { continue; }
+        let name = zip_entry_name(relative);
+        if known_names.contains(&name) { continue; }
+        let is_dir = entry.file_type().is_dir();
+        let options = zip::write::FileOptions::default()
+            .compression_method(compression_for_path(entry.path()))
+            .unix_permissions(0o755)
+            .large_file(true);
+        planned.push(WriteBackEntry { name: if is_dir { format!("{}/", name) } else { name }, is_dir, options });
+    }
+    Ok(planned)
+}
+
+fn verify_written_zip(zip_path: &Path, dir_path: &Path, planned: &[WriteBackEntry]) -> Result<(), AppError> {
+    let mut archive = ZipArchive::new(File::open(zip_path)?)?;
+    if archive.len() != planned.len() {
+        return Err(AppError::internal(format!("Verification failed: expected {} entries, found {}", planned.len(), archive.len())));
+    }
+    let mut sink = Vec::new();
+    for (i, expected) in planned.iter().enumerate() {
+        let mut file = archive.by_index(i)?;
+        if file.name() != expected.name {
+            return Err(AppError::internal(format!("Verification failed: entry {} is '{}', expected '{}'", i, file.name(), expected.name)));
+        }
+        if expected.is_dir { continue; }
+        // Reading to the end makes the zip crate check the CRC32 of the entry.
+        sink.clear();
+        file.read_to_end(&mut sink)?;
+        let on_disk_len = fs::metadata(dir_path.join(&expected.name))?.len();
+        if sink.len() as u64 != on_disk_len {
+            return Err(AppError::internal(format!("Verification failed: size mismatch for '{}'", expected.name)));
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites the archive at `original_path` from the working folder, through a temporary file that replaces it
+/// once it reads back fine.
+fn write_back_archive(dir_path: &Path, original_path: &Path, mut on_progress: impl FnMut(f64, &str)) -> Result<(), AppError> {
+    let mut original = ZipArchive::new(File::open(original_path)?)?;
+    let planned = plan_write_back(&mut original, dir_path)?;
+    drop(original);
+
+    let mut temp_name = original_path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = original_path.with_file_name(temp_name);
+
+    let write_result = (|| -> Result<(), AppError> {
+        let mut zip = zip::ZipWriter::new(File::create(&temp_path)?);
+        let mut buffer = Vec::new();
+        for (i, entry) in planned.iter().enumerate() {
+            if entry.is_dir {
+                zip.add_directory(entry.name.clone(), entry.options)?;
+                continue;
+            }
+            zip.start_file(entry.name.clone(), entry.options)?;
+            let mut f = File::open(dir_path.join(&entry.name))?;
+            f.read_to_end(&mut buffer)?;
+            zip.write_all(&buffer)?;
+            buffer.clear();
+            on_progress((i + 1) as f64 / planned.len() as f64, &entry.name);
+        }
+        zip.finish()?.sync_all()?;
+        verify_written_zip(&temp_path, dir_path, &planned)?;
+        fs::rename(&temp_path, original_path)?;
+        Ok(())
+    })();
+    if write_result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    write_result
 }
 
 #[tauri::command]
-fn get_layout_list_from_c3proj(project_c3proj_path: String) -> Result<Vec<LayoutEntry>, String> {
+async fn write_back_c3p(app: tauri::AppHandle, window: tauri::Window, dir_path: String, zip_path: String) -> Result<(), AppError> {
+    let data_dir = snapshot::app_data_dir(&app)?;
+    let file_for_errors = zip_path.clone();
+    let task = tokio::spawn(async move {
+        window.emit("progress", Payload { progress: 0.0, filename: "".into(), done: false, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+        write_back_archive(Path::new(&dir_path), Path::new(&zip_path), |progress, filename| {
+            window.emit("progress", Payload { progress, filename: filename.into(), done: false, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+        })?;
+        // The archive now holds the folder's content, reopening it must not drop the history.
+        journal::set_source_hash(&data_dir, Path::new(&dir_path), &snapshot::file_hash(Path::new(&zip_path))?)?;
+        window.emit("progress", Payload { progress: 1.0, filename: "".into(), done: true, ..Default::default() }).unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+        Ok::<(), AppError>(())
+    });
+    task.await?.map_err(|e: AppError| e.with_file(&file_for_errors))
+}
+
+#[tauri::command]
+fn get_layout_list_from_c3proj(project_c3proj_path: String) -> Result<Vec<LayoutEntry>, AppError> {
     let content = fs::read_to_string(&project_c3proj_path)
-        .map_err(|e| format!("Failed to read project.c3proj: {}", e))?;
-    let mut reader = Reader::from_str(&content);
+        .map_err(|e| AppError::io(&project_c3proj_path, e))?;
+    parse_layout_list_xml(&content).map_err(|e| e.with_file(&project_c3proj_path))
+}
+
+fn parse_layout_list_xml(content: &str) -> Result<Vec<LayoutEntry>, AppError> {
+    let mut reader = Reader::from_str(content);
     reader.trim_text(true);
     let mut buf = Vec::new();
     let mut layouts = Vec::new();
@@ -221,7 +487,9 @@ fn get_layout_list_from_c3proj(project_c3proj_path: String) -> Result<Vec<Layout
                 }
             }
             Ok(Event::Text(e)) => {
-                let text_content = e.unescape().map_err(|err| format!("XML unescape error: {}", err))?.into_owned();
+                let text_content = e.unescape()
+                    .map_err(|err| AppError::new(AppErrorKind::Xml, format!("XML unescape error: {}", err)).at_xml_position(reader.buffer_position() as u64))?
+                    .into_owned();
                 if next_text_is_name {
                     current_layout_name = Some(text_content);
                     next_text_is_name = false;
@@ -247,7 +515,7 @@ fn get_layout_list_from_c3proj(project_c3proj_path: String) -> Result<Vec<Layout
                 }
             }
             Ok(Event::Eof) => break,
-            Err(e) => return Err(format!("XML Error at position {}: {:?}", reader.buffer_position(), e)),
+            Err(e) => return Err(AppError::new(AppErrorKind::Xml, e.to_string()).at_xml_position(reader.buffer_position() as u64)),
             _ => (),
         }
         buf.clear();
@@ -255,33 +523,21 @@ fn get_layout_list_from_c3proj(project_c3proj_path: String) -> Result<Vec<Layout
     Ok(layouts)
 }
 
-#[tauri::command]
-fn get_project_defined_templates(project_path_str: String, layout_entries: Vec<LayoutEntry>) -> Result<Vec<TemplateDefinition>, String> {
-    let project_path = PathBuf::from(project_path_str);
+fn find_project_templates(project_path: &Path, layout_entries: &[LayoutEntry]) -> Result<WithWarnings<Vec<TemplateDefinition>>, AppError> {
     let c3proj_path = project_path.join("project.c3proj");
     let mut found_templates = HashSet::new();
+    let mut warnings = Vec::new();
 
     if !c3proj_path.exists() {
-        return Err("project.c3proj not found in project root.".to_string());
+        return Err(AppError::not_found(&c3proj_path));
     }
 
     for layout_entry in layout_entries {
         let full_layout_path = project_path.join(&layout_entry.path); 
-        if !full_layout_path.exists() {
-            eprintln!("Warning: Layout file listed in c3proj not found: {:?} (referenced by {})", full_layout_path, layout_entry.path);
-            continue;
-        }
-        let content = match fs::read_to_string(&full_layout_path) {
-            Ok(c) => c,
+        let layout_data = match read_json_file(&full_layout_path) {
+            Ok((d, _)) => d,
             Err(e) => {
-                eprintln!("Error reading layout file {:?}: {}", full_layout_path, e);
-                continue;
-            }
-        };
-        let layout_data: Value = match serde_json::from_str(&content) {
-            Ok(d) => d,
-            Err(e) => {
-                eprintln!("Error parsing layout JSON {:?}: {}", full_layout_path, e);
+                warnings.push(e);
                 continue;
             }
         };
@@ -315,202 +571,295 @@ fn get_project_defined_templates(project_path_str: String, layout_entries: Vec<L
                     }
                 }
             }
+        } else {
+            warnings.push(AppError::new(AppErrorKind::InvalidStructure, "Layout has no 'layers' array").with_file(&full_layout_path).at_pointer("/layers"));
+        }
+    }
+    Ok(WithWarnings { value: found_templates.into_iter().collect(), warnings })
+}
+
+#[tauri::command]
+fn get_project_defined_templates(project_path_str: String, layout_entries: Vec<LayoutEntry>) -> Result<WithWarnings<Vec<TemplateDefinition>>, AppError> {
+    find_project_templates(Path::new(&project_path_str), &layout_entries)
+}
+
+/// Every definition of the template named `template_name` (one per object type), erroring if there is none.
+fn find_template_definitions(project_path: &Path, template_name: &str, warnings: &mut Vec<AppError>) -> Result<Vec<TemplateDefinition>, AppError> {
+    let project = project::Project::load(project_path)?;
+    let mut templates = find_project_templates(project_path, &project.layout_entries)?;
+    warnings.append(&mut templates.warnings);
+    let definitions: Vec<TemplateDefinition> = templates.value.into_iter()
+        .filter(|t| t.name == template_name)
+        .collect();
+    if definitions.is_empty() {
+        return Err(AppError::new(AppErrorKind::NotFound, format!("No template named '{}' exists in the project", template_name)));
+    }
+    Ok(definitions)
+}
+
+/// Plans the whole hierarchy of every root: the roots become replicas of `template_name` and their children
+/// replicas of the matching template children. Returns the template name per UID.
+fn plan_replica_assignments(
+    project_path: &Path,
+    layout_data: &Value,
+    roots: &[(u32, String)],
+    template_name: &str,
+    definitions: &[TemplateDefinition],
+    rejected: &mut Vec<(u32, String)>,
+) -> Result<HashMap<u32, String>, AppError> {
+    let by_uid = templates::index_by_uid(layout_data);
+    let mut assignments: HashMap<u32, String> = roots.iter().map(|(uid, _)| (*uid, template_name.to_string())).collect();
+    let mut trees = HashMap::new();
+    for (uid, object_type) in roots {
+        let Some(definition) = definitions.iter().find(|d| &d.object_type == object_type) else { continue };
+        if !trees.contains_key(object_type) {
+            trees.insert(object_type.clone(), templates::template_tree(project_path, definition)?);
         }
+        templates::plan_replica_hierarchy(&by_uid, *uid, &trees[object_type], &mut assignments, rejected);
     }
-    Ok(found_templates.into_iter().collect())
+    Ok(assignments)
+}
+
+fn describe_types(object_types: &HashSet<String>) -> String {
+    let mut names: Vec<&str> = object_types.iter().map(|t| t.as_str()).collect();
+    names.sort();
+    names.join(", ")
 }
 
 
 #[tauri::command]
-fn get_layout_instances_info(project_path: String, layout_file_path: String) -> Result<Vec<InstanceDisplayInfo>, String> {
+fn get_layout_instances_info(project_path: String, layout_file_path: String) -> Result<Vec<InstanceDisplayInfo>, AppError> {
     let full_layout_path = PathBuf::from(&project_path).join(&layout_file_path);
-    if !full_layout_path.exists() {
-        return Err(format!("Layout file not found: {:?}", full_layout_path));
-    }
-    let content = fs::read_to_string(&full_layout_path)
-        .map_err(|e| format!("Failed to read layout file {:?}: {}", full_layout_path, e))?;
-    let layout_data: Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse layout JSON {:?}: {}", full_layout_path, e))?;
+    let (layout_data, _) = read_json_file(&full_layout_path)?;
     let mut instances_display_info = Vec::new();
+    let instances = templates::layout_instances(&layout_data);
+    // Only the parent lists its children, so parents are looked up from there.
+    let mut parents = HashMap::new();
+    for (_, instance_obj) in &instances {
+        for child_uid in templates::instance_children(instance_obj) {
+            parents.insert(child_uid, templates::instance_uid(instance_obj));
+        }
+    }
 
-    if let Some(layers) = layout_data.get("layers").and_then(|l| l.as_array()) {
-        for layer in layers {
-            if let Some(instances_arr) = layer.get("instances").and_then(|i| i.as_array()) {
-                for instance_val in instances_arr {
-                    if let Some(instance_obj) = instance_val.as_object() {
-                        let uid = instance_obj.get("uid").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
-                        let object_type_name = instance_obj.get("type").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                        
-                        let world_coords = instance_obj.get("world").and_then(|w| w.as_object());
-                        let x = world_coords.and_then(|w| w.get("x")).and_then(|v| v.as_f64()).unwrap_or(0.0);
-                        let y = world_coords.and_then(|w| w.get("y")).and_then(|v| v.as_f64()).unwrap_or(0.0);
-                        let mut is_replica = false;
-                        let mut is_template = false;
-                        let mut template_or_source_name: Option<String> = None;
-
-                        if let Some(template_val) = instance_obj.get("template") {
-                            if let Some(template_obj_map) = template_val.as_object() {
-                                if let Some(mode_str) = template_obj_map.get("mode").and_then(|m| m.as_str()) {
-                                    if mode_str == "replica" {
-                                        is_replica = true;
-                                        template_or_source_name = template_obj_map.get("sourceTemplateName").and_then(|n| n.as_str()).map(String::from);
-                                    } else if mode_str == "template" {
-                                        is_template = true;
-                                        template_or_source_name = template_obj_map.get("templateName").and_then(|n| n.as_str()).map(String::from);
-                                    }
-                                }
-                            }
-                        }
-                        instances_display_info.push(InstanceDisplayInfo {
-                            uid,
-                            object_type_name,
-                            is_replica,
-                            is_template,
-                            template_or_source_name,
-                            x,
-                            y,
-                        });
+    let layers = templates::layout_layers(&layout_data);
+    let layer_instances = layers.iter().enumerate().flat_map(|(layer_index, (_, layer))| {
+        let layer_name = layer.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+        layer.get("instances").and_then(|i| i.as_array()).into_iter().flatten()
+            .filter_map(|instance| instance.as_object())
+            .enumerate()
+            .map(move |(z_index, instance_obj)| (layer_index, layer_name, z_index, instance_obj))
+    });
+    for (layer_index, layer_name, z_index, instance_obj) in layer_instances {
+        let uid = templates::instance_uid(instance_obj);
+        let object_type_name = instance_obj.get("type").and_then(|v| v.as_str()).unwrap_or("").to_string();
+
+        let world_coords = instance_obj.get("world").and_then(|w| w.as_object());
+        let x = world_coords.and_then(|w| w.get("x")).and_then(|v| v.as_f64()).unwrap_or(0.0);
+        let y = world_coords.and_then(|w| w.get("y")).and_then(|v| v.as_f64()).unwrap_or(0.0);
+        let world_number = |key: &str| world_coords.and_then(|w| w.get(key)).and_then(|v| v.as_f64());
+        let opacity = world_coords.and_then(|w| w.get("color")).and_then(|c| c.get(3)).and_then(|v| v.as_f64()).unwrap_or(1.0);
+        let behaviors = instance_obj.get("behaviors").and_then(|b| b.as_object())
+            .map(|behaviors| behaviors.iter()
+                .map(|(name, behavior)| (name.clone(), behavior.get("properties").cloned().unwrap_or_else(|| Value::Object(serde_json::Map::new()))))
+                .collect())
+            .unwrap_or_default();
+        let mut is_replica = false;
+        let mut is_template = false;
+        let mut template_or_source_name: Option<String> = None;
+
+        if let Some(template_val) = instance_obj.get("template") {
+            if let Some(template_obj_map) = template_val.as_object() {
+                if let Some(mode_str) = template_obj_map.get("mode").and_then(|m| m.as_str()) {
+                    if mode_str == "replica" {
+                        is_replica = true;
+                        template_or_source_name = template_obj_map.get("sourceTemplateName").and_then(|n| n.as_str()).map(String::from);
+                    } else if mode_str == "template" {
+                        is_template = true;
+                        template_or_source_name = template_obj_map.get("templateName").and_then(|n| n.as_str()).map(String::from);
                     }
                 }
             }
         }
+        instances_display_info.push(InstanceDisplayInfo {
+            uid,
+            object_type_name,
+            is_replica,
+            is_template,
+            template_or_source_name,
+            x,
+            y,
+            width: world_number("width").unwrap_or(0.0),
+            height: world_number("height").unwrap_or(0.0),
+            angle: world_number("angle").unwrap_or(0.0),
+            opacity,
+            layer_name: layer_name.to_string(),
+            layer_index,
+            z_index,
+            instance_variables: instance_obj.get("instanceVariables").and_then(|v| v.as_object()).cloned().unwrap_or_default(),
+            behaviors,
+            parent_uid: parents.get(&uid).copied(),
+            children_uids: templates::instance_children(instance_obj),
+        });
     }
     Ok(instances_display_info)
 }
 
+/// Turns the instance into a replica of `target_template_name`. Returns `None` when its
+/// `template` value isn't an object, leaving it untouched.
+fn make_replica(instance_obj: &mut serde_json::Map<String, Value>, uid: u32, target_template_name: &str) -> Option<ReplicaChange> {
+    let before = instance_obj.get("template").cloned();
+    let template_obj = instance_obj.entry("template".to_string())
+        .or_insert_with(|| Value::Object(serde_json::Map::new()))
+        .as_object_mut()?;
+    template_obj.insert("mode".to_string(), Value::String("replica".to_string()));
+    template_obj.insert("sourceTemplateName".to_string(), Value::String(target_template_name.to_string()));
+    template_obj.insert("templateName".to_string(), Value::String("".to_string())); // Clear specific template name for replicas
+    templates::insert_template_defaults(template_obj);
+
+    let current_mode = before.as_ref().and_then(|t| t.get("mode")).and_then(|m| m.as_str()).map(String::from);
+    let template_diff = json_diff(before.as_ref(), instance_obj.get("template"));
+    Some(ReplicaChange { uid, current_mode, template_diff })
+}
+
 #[tauri::command]
-fn set_instances_as_replicas(payload: SetReplicasPayload) -> Result<(), String> {
-    let full_layout_path = PathBuf::from(&payload.project_path).join(&payload.layout_file_path);
-    if !full_layout_path.exists() {
-        return Err(format!("Layout file not found: {:?}", full_layout_path));
-    }
-    let content = fs::read_to_string(&full_layout_path)
-        .map_err(|e| format!("Failed to read layout file {:?}: {}", full_layout_path, e))?;
-    let mut layout_data: Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse layout JSON {:?}: {}", full_layout_path, e))?;
-    let mut modified_count = 0;
-
-    if let Some(layers) = layout_data.get_mut("layers").and_then(|l| l.as_array_mut()) {
-        for layer in layers {
-            if let Some(instances_arr) = layer.get_mut("instances").and_then(|i| i.as_array_mut()) {
-                for instance_val in instances_arr {
-                    if let Some(instance_obj) = instance_val.as_object_mut() {
-                        if let Some(uid_val) = instance_obj.get("uid").and_then(|v| v.as_u64()) {
-                            let uid = uid_val as u32;
-                            if payload.instance_uids.contains(&uid) {
-                                let template_map_entry = instance_obj.entry("template".to_string())
-                                    .or_insert_with(|| Value::Object(serde_json::Map::new()));
-                                if let Some(template_obj) = template_map_entry.as_object_mut() {
-                                    template_obj.insert("mode".to_string(), Value::String("replica".to_string()));
-                                    template_obj.insert("sourceTemplateName".to_string(), Value::String(payload.target_template_name.clone()));
-                                    template_obj.insert("templateName".to_string(), Value::String("".to_string()));
-                                    template_obj.entry("replicaHierarchyInSyncWithTemplate".to_string()).or_insert(Value::Bool(true));
-                                    template_obj.entry("templatePropagateHierarchyChanges".to_string()).or_insert(Value::Bool(true));
-                                    template_obj.entry("replicaIgnoreTemplateHierarchyChanges".to_string()).or_insert(Value::Bool(false));
-                                    template_obj.entry("components".to_string()).or_insert_with(|| Value::Array(vec![]));
-                                    
-                                    modified_count += 1;
-                                } else {
-                                    return Err("Internal error: Failed to ensure template object is a map".to_string());
-                                }
-                            }
-                        }
-                    }
-                }
+fn set_instances_as_replicas(app: tauri::AppHandle, payload: SetReplicasPayload) -> Result<WithWarnings<ReplicaConversionResult>, AppError> {
+    let project_base_path = PathBuf::from(&payload.project_path);
+    let full_layout_path = project_base_path.join(&payload.layout_file_path);
+    let mut warnings = Vec::new();
+    let definitions = find_template_definitions(&project_base_path, &payload.target_template_name, &mut warnings)?;
+    let template_types: HashSet<String> = definitions.iter().map(|d| d.object_type.clone()).collect();
+    let (mut layout_data, _) = read_json_file(&full_layout_path)?;
+    if layout_data.get("layers").and_then(|l| l.as_array()).is_none() {
+        return Err(AppError::new(AppErrorKind::InvalidStructure, "Could not find 'layers' array in layout file")
+            .with_file(&full_layout_path)
+            .at_pointer("/layers"));
+    }
+    let mut instance_changes = Vec::new();
+    let mut rejected = Vec::new();
+
+    let mut roots = Vec::new();
+    for (_, instance_obj) in templates::layout_instances(&layout_data) {
+        let uid = templates::instance_uid(instance_obj);
+        if !payload.instance_uids.contains(&uid) {
+            continue;
+        }
+        let instance_type = instance_obj.get("type").and_then(|t| t.as_str()).unwrap_or_default();
+        let template = instance_obj.get("template").and_then(|t| t.as_object());
+        let reason = if !template_types.contains(instance_type) {
+            Some(format!("Template '{}' is defined for {}, not '{}'", payload.target_template_name, describe_types(&template_types), instance_type))
+        } else if template.and_then(|t| t.get("mode")).and_then(|m| m.as_str()) == Some("template")
+            && template.and_then(|t| t.get("templateName")).and_then(|n| n.as_str()) == Some(payload.target_template_name.as_str()) {
+            Some(format!("Instance is the '{}' template itself", payload.target_template_name))
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => rejected.push((uid, reason)),
+            None => roots.push((uid, instance_type.to_string())),
+        }
+    }
+    let assignments = plan_replica_assignments(&project_base_path, &layout_data, &roots, &payload.target_template_name, &definitions, &mut rejected)?;
+
+    for (pointer, instance_obj) in templates::layout_instances_mut(&mut layout_data) {
+        let uid = templates::instance_uid(instance_obj);
+        let Some(source_template_name) = assignments.get(&uid) else { continue };
+        match make_replica(instance_obj, uid, source_template_name) {
+            Some(change) => instance_changes.push(change),
+            None => {
+                return Err(AppError::new(AppErrorKind::InvalidStructure, format!("Instance {} has a 'template' value that is not an object", uid))
+                    .with_file(&full_layout_path)
+                    .at_pointer(format!("{}/template", pointer)));
             }
         }
-    } else {
-        return Err(format!("Could not find 'layers' array in layout file: {:?}", full_layout_path));
     }
-    if modified_count == 0 && !payload.instance_uids.is_empty() {
-        eprintln!("Warning: No instances were modified for UIDs: {:?}. Target UIDs might not exist or layout structure differs.", payload.instance_uids);
+    let rejected: Vec<RejectedInstance> = rejected.into_iter()
+        .map(|(uid, reason)| RejectedInstance { layout_path: payload.layout_file_path.clone(), uid, reason })
+        .collect();
+    if instance_changes.is_empty() && rejected.is_empty() && !payload.instance_uids.is_empty() {
+        warnings.push(AppError::new(AppErrorKind::NotFound, format!("No instances were modified for UIDs: {:?}. Target UIDs might not exist or layout structure differs.", payload.instance_uids))
+            .with_file(&full_layout_path));
     }
-    let updated_content = serde_json::to_string_pretty(&layout_data)
-        .map_err(|e| format!("Failed to serialize layout JSON: {}", e))?;
-    fs::write(&full_layout_path, updated_content)
-        .map_err(|e| format!("Failed to write updated layout file {:?}: {}", full_layout_path, e))?;
-    Ok(())
+    let modified_count = instance_changes.len();
+    let message = if payload.dry_run {
+        format!("Dry run: {} instances in {} would become replicas of '{}', {} rejected.", modified_count, payload.layout_file_path, payload.target_template_name, rejected.len())
+    } else if modified_count == 0 {
+        format!("No instances in {} were modified, {} rejected.", payload.layout_file_path, rejected.len())
+    } else {
+        let mut changes = ChangeSet::new(&project_base_path, "set_instances_as_replicas");
+        changes.write_json(full_layout_path, &layout_data)?;
+        changes.commit(&snapshot::app_data_dir(&app)?)?;
+        format!("Successfully modified {} instances in {} to be replicas of '{}', {} rejected.", modified_count, payload.layout_file_path, payload.target_template_name, rejected.len())
+    };
+    let layouts = vec![LayoutReplicaChanges { layout_path: payload.layout_file_path, instances: instance_changes }];
+    Ok(WithWarnings { value: ReplicaConversionResult { dry_run: payload.dry_run, message, layouts, rejected }, warnings })
 }
 
 #[tauri::command]
-fn set_all_instances_of_type_as_replicas_project_wide(payload: SetAllInstancesOfTypePayload) -> Result<String, String> {
+fn set_all_instances_of_type_as_replicas_project_wide(app: tauri::AppHandle, payload: SetAllInstancesOfTypePayload) -> Result<WithWarnings<ReplicaConversionResult>, AppError> {
     let project_base_path = PathBuf::from(&payload.project_path);
-    let mut modified_layouts_count = 0;
+    let mut changes = ChangeSet::new(&project_base_path, "set_all_instances_of_type_as_replicas_project_wide");
+    let mut layouts = Vec::new();
     let mut total_instances_modified = 0;
+    let mut warnings = Vec::new();
+    let mut rejected = Vec::new();
+    let definitions = find_template_definitions(&project_base_path, &payload.target_template_name, &mut warnings)?;
+    let template_types: HashSet<String> = definitions.iter().map(|d| d.object_type.clone()).collect();
+    if !template_types.contains(&payload.object_type_name) {
+        return Err(AppError::new(AppErrorKind::InvalidInput, format!("Template '{}' is defined for {}, not '{}'", payload.target_template_name, describe_types(&template_types), payload.object_type_name)));
+    }
 
     for layout_relative_path_str in payload.layout_file_paths {
         let full_layout_path = project_base_path.join(&layout_relative_path_str);
-        if !full_layout_path.exists() {
-            eprintln!("Warning: Layout file not found during project-wide update: {:?}. Skipping.", full_layout_path);
-            continue;
-        }
-
-        let content = match fs::read_to_string(&full_layout_path) {
-            Ok(c) => c,
+        let mut layout_data = match read_json_file(&full_layout_path) {
+            Ok((d, _)) => d,
             Err(e) => {
-                eprintln!("Error reading layout file {:?}: {}. Skipping.", full_layout_path, e);
-                continue;
-            }
-        };
-        let mut layout_data: Value = match serde_json::from_str(&content) {
-            Ok(d) => d,
-            Err(e) => {
-                eprintln!("Error parsing layout JSON {:?}: {}. Skipping.", full_layout_path, e);
+                warnings.push(e);
                 continue;
             }
         };
 
-        let mut layout_modified_this_iteration = false;
-        if let Some(layers) = layout_data.get_mut("layers").and_then(|l| l.as_array_mut()) {
-            for layer in layers {
-                if let Some(instances_arr) = layer.get_mut("instances").and_then(|i| i.as_array_mut()) {
-                    for instance_val in instances_arr {
-                        if let Some(instance_obj) = instance_val.as_object_mut() {
-                            if let Some(instance_type_val) = instance_obj.get("type").and_then(|t| t.as_str()) {
-                                if instance_type_val == payload.object_type_name {
-                                    // Skip modifying instances that are themselves templates of any kind
-                                    let is_already_template = instance_obj.get("template")
-                                        .and_then(|t_val| t_val.as_object())
-                                        .and_then(|t_obj| t_obj.get("mode"))
-                                        .and_then(|m_val| m_val.as_str()) == Some("template");
-
-                                    if is_already_template {
-                                        continue; 
-                                    }
+        if layout_data.get("layers").and_then(|l| l.as_array()).is_none() {
+            warnings.push(AppError::new(AppErrorKind::InvalidStructure, "Layout has no 'layers' array, skipped").with_file(&full_layout_path).at_pointer("/layers"));
+            continue;
+        }
+        // Instances that are themselves templates of any kind are left alone
+        let roots: Vec<(u32, String)> = templates::layout_instances(&layout_data).into_iter()
+            .map(|(_, instance_obj)| instance_obj)
+            .filter(|instance_obj| instance_obj.get("type").and_then(|t| t.as_str()) == Some(payload.object_type_name.as_str()))
+            .filter(|instance_obj| instance_obj.get("template")
+                .and_then(|t_val| t_val.as_object())
+                .and_then(|t_obj| t_obj.get("mode"))
+                .and_then(|m_val| m_val.as_str()) != Some("template"))
+            .map(|instance_obj| (templates::instance_uid(instance_obj), payload.object_type_name.clone()))
+            .collect();
+        let mut layout_rejected = Vec::new();
+        let assignments = plan_replica_assignments(&project_base_path, &layout_data, &roots, &payload.target_template_name, &definitions, &mut layout_rejected)?;
+        rejected.extend(layout_rejected.into_iter()
+            .map(|(uid, reason)| RejectedInstance { layout_path: layout_relative_path_str.clone(), uid, reason }));
 
-                                    let template_map_entry = instance_obj.entry("template".to_string())
-                                        .or_insert_with(|| Value::Object(serde_json::Map::new()));
-                                    
-                                    if let Some(template_obj) = template_map_entry.as_object_mut() {
-                                        template_obj.insert("mode".to_string(), Value::String("replica".to_string()));
-                                        template_obj.insert("sourceTemplateName".to_string(), Value::String(payload.target_template_name.clone()));
-                                        template_obj.insert("templateName".to_string(), Value::String("".to_string())); // Clear specific template name for replicas
-                                        // Keep existing or add defaults for other replica properties
-                                        template_obj.entry("replicaHierarchyInSyncWithTemplate".to_string()).or_insert(Value::Bool(true));
-                                        template_obj.entry("templatePropagateHierarchyChanges".to_string()).or_insert(Value::Bool(true));
-                                        template_obj.entry("replicaIgnoreTemplateHierarchyChanges".to_string()).or_insert(Value::Bool(false));
-                                        template_obj.entry("components".to_string()).or_insert_with(|| Value::Array(vec![]));
-                                        
-                                        total_instances_modified += 1;
-                                        layout_modified_this_iteration = true;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        let mut instance_changes = Vec::new();
+        for (_, instance_obj) in templates::layout_instances_mut(&mut layout_data) {
+            let uid = templates::instance_uid(instance_obj);
+            if let Some(change) = assignments.get(&uid).and_then(|name| make_replica(instance_obj, uid, name)) {
+                instance_changes.push(change);
             }
         }
 
-        if layout_modified_this_iteration {
-            let updated_content = serde_json::to_string_pretty(&layout_data)
-                .map_err(|e| format!("Failed to serialize layout JSON for {:?}: {}", full_layout_path, e))?;
-            fs::write(&full_layout_path, updated_content)
-                .map_err(|e| format!("Failed to write updated layout file {:?}: {}", full_layout_path, e))?;
-            modified_layouts_count += 1;
+        if !instance_changes.is_empty() {
+            if !payload.dry_run {
+                changes.write_json(full_layout_path, &layout_data)?;
+            }
+            total_instances_modified += instance_changes.len();
+            layouts.push(LayoutReplicaChanges { layout_path: layout_relative_path_str, instances: instance_changes });
         }
     }
-    Ok(format!("Successfully modified {} instances of type '{}' across {} layouts to be replicas of '{}'.", total_instances_modified, payload.object_type_name, modified_layouts_count, payload.target_template_name))
+    let message = if payload.dry_run {
+        format!("Dry run: {} instances of type '{}' across {} layouts would become replicas of '{}'.", total_instances_modified, payload.object_type_name, layouts.len(), payload.target_template_name)
+    } else {
+        changes.commit(&snapshot::app_data_dir(&app)?)?;
+        format!("Successfully modified {} instances of type '{}' across {} layouts to be replicas of '{}'.", total_instances_modified, payload.object_type_name, layouts.len(), payload.target_template_name)
+    };
+    Ok(WithWarnings { value: ReplicaConversionResult { dry_run: payload.dry_run, message, layouts, rejected }, warnings })
 }
 
 fn main() {
@@ -519,12 +868,183 @@ fn main() {
             read_png_dimensions,
             extract_zip,
             save_zip,
+            write_back_c3p,
             get_layout_list_from_c3proj,
+            project::load_project,
+            indexer::index_project,
+            images::apply_image_actions,
             get_project_defined_templates,
             get_layout_instances_info,
             set_instances_as_replicas,
-            set_all_instances_of_type_as_replicas_project_wide
+            set_all_instances_of_type_as_replicas_project_wide,
+            snapshot::list_snapshots,
+            snapshot::restore_snapshot,
+            journal::get_journal,
+            journal::undo_operation,
+            journal::redo_operation,
+            changes::write_project_json,
+            templates::sync_replicas_with_template,
+            templates::detach_replicas,
+            templates::retire_template,
+            templates::rename_template,
+            templates::promote_instance_to_template,
+            layout::get_layout,
+            layout::rename_layer,
+            layout::move_layer,
+            layout::duplicate_layer,
+            layout::delete_layer,
+            layout::move_instances_to_layer,
+            uids::find_duplicate_uids,
+            uids::repair_duplicate_uids,
+            bulk_edit::bulk_edit_instances,
+            object_types::replace_object_type
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("construct-crawler-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn archive_with(entries: &[(&str, &[u8])], symlinks: &[(&str, &str)]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        for (name, target) in symlinks {
+            zip.add_symlink(*name, *target, zip::write::FileOptions::default()).unwrap();
+        }
+        ZipArchive::new(zip.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn extract_rejects_parent_traversal() {
+        let root = temp_dir("traversal");
+        let dest = root.join("dest");
+        let mut archive = archive_with(&[("project.c3proj", b"{}"), ("../evil.txt", b"x"), ("a/../../evil2.txt", b"x")], &[]);
+        let rejected = extract_archive(&mut archive, &dest, |_, _| {}).unwrap();
+        let names: Vec<_> = rejected.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["../evil.txt", "a/../../evil2.txt"]);
+        assert!(dest.join("project.c3proj").is_file());
+        assert!(!root.join("evil.txt").exists());
+        assert!(!root.join("evil2.txt").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn extract_rejects_absolute_paths() {
+        let root = temp_dir("absolute");
+        let target = root.join("absolute.txt");
+        let absolute_name = target.to_string_lossy().into_owned();
+        let mut archive = archive_with(&[(absolute_name.as_str(), b"x")], &[]);
+        let rejected = extract_archive(&mut archive, &root.join("dest"), |_, _| {}).unwrap();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].name, absolute_name);
+        assert!(!target.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn extract_rejects_symlinks() {
+        let root = temp_dir("symlink");
+        let dest = root.join("dest");
+        let mut archive = archive_with(&[("images/ok.png", b"png")], &[("images/link.png", "../../outside.png")]);
+        let rejected = extract_archive(&mut archive, &dest, |_, _| {}).unwrap();
+        assert_eq!(rejected, vec![RejectedEntry { name: "images/link.png".into(), reason: "Symbolic links are not allowed".into() }]);
+        assert!(dest.join("images/ok.png").is_file());
+        assert!(fs::symlink_metadata(dest.join("images/link.png")).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    fn date_time_parts(time: zip::DateTime) -> (u16, u8, u8, u8, u8, u8) {
+        (time.year(), time.month(), time.day(), time.hour(), time.minute(), time.second())
+    }
+
+    #[test]
+    fn zip_date_time_uses_local_time() {
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let local = chrono::DateTime::<chrono::Local>::from(time);
+        let expected = (local.year() as u16, local.month() as u8, local.day() as u8, local.hour() as u8, local.minute() as u8, local.second() as u8);
+        assert_eq!(zip_date_time(time).map(date_time_parts), Some(expected));
+        assert!(zip_date_time(std::time::UNIX_EPOCH).is_none());
+    }
+
+    type EntrySummary = (String, Vec<u8>, zip::CompressionMethod, (u16, u8, u8, u8, u8, u8));
+
+    /// Name, content, compression and time of every entry.
+    fn archive_entries(path: &Path) -> Vec<EntrySummary> {
+        let mut archive = ZipArchive::new(File::open(path).unwrap()).unwrap();
+        (0..archive.len()).map(|i| {
+            let mut file = archive.by_index(i).unwrap();
+            let mut content = Vec::new();
+            file.read_to_end(&mut content).unwrap();
+            (file.name().to_string(), content, file.compression(), date_time_parts(file.last_modified()))
+        }).collect()
+    }
+
+    #[test]
+    fn write_back_only_changes_edited_entries() {
+        let root = temp_dir("write-back");
+        let c3p = root.join("game.c3p");
+        let original_time = zip::DateTime::from_date_and_time(2020, 1, 2, 3, 4, 6).unwrap();
+        let options = zip::write::FileOptions::default().last_modified_time(original_time);
+        let mut zip = zip::ZipWriter::new(File::create(&c3p).unwrap());
+        zip.start_file("project.c3proj", options).unwrap();
+        zip.write_all(b"{ \"name\": \"Game\" }").unwrap();
+        zip.add_directory("layouts/", options).unwrap();
+        zip.start_file("layouts/Main.json", options).unwrap();
+        zip.write_all(b"{ \"width\": 100 }").unwrap();
+        zip.start_file("media/music.webm", options.compression_method(zip::CompressionMethod::Stored)).unwrap();
+        zip.write_all(b"webm").unwrap();
+        zip.finish().unwrap();
+        let original = archive_entries(&c3p);
+
+        // The working folder as extracted, then edited.
+        let work = root.join("work");
+        fs::create_dir_all(work.join("layouts")).unwrap();
+        fs::create_dir_all(work.join("media")).unwrap();
+        fs::write(work.join("project.c3proj"), b"{ \"name\": \"Game\" }").unwrap();
+        fs::write(work.join("media/music.webm"), b"webm").unwrap();
+        let saved_at = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let edited = work.join("layouts/Main.json");
+        fs::write(&edited, b"{ \"width\": 200 }").unwrap();
+        File::options().write(true).open(&edited).unwrap().set_modified(saved_at).unwrap();
+        fs::create_dir_all(work.join("scripts")).unwrap();
+        fs::write(work.join("scripts/main.js"), b"runOnStartup();").unwrap();
+
+        write_back_archive(&work, &c3p, |_, _| {}).unwrap();
+        let written = archive_entries(&c3p);
+        let names: Vec<&str> = written.iter().map(|(name, ..)| name.as_str()).collect();
+        assert_eq!(names, ["project.c3proj", "layouts/", "layouts/Main.json", "media/music.webm", "scripts/", "scripts/main.js"]);
+        for (before, after) in original.iter().zip(&written) {
+            if before.0 == "layouts/Main.json" {
+                assert_eq!(after.1, b"{ \"width\": 200 }");
+                assert_eq!(after.2, before.2);
+                assert_eq!(Some(after.3), zip_date_time(saved_at).map(date_time_parts));
+            } else {
+                assert_eq!(after, before);
+            }
+        }
+        assert!(!root.join("game.c3p.tmp").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn extract_keeps_safe_dot_dot_inside_destination() {
+        let root = temp_dir("inside");
+        let dest = root.join("dest");
+        let mut archive = archive_with(&[("layouts/../layouts/Main.json", b"{}")], &[]);
+        let rejected = extract_archive(&mut archive, &dest, |_, _| {}).unwrap();
+        assert!(rejected.is_empty());
+        assert!(dest.join("layouts/Main.json").is_file());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{AppError, AppErrorKind, WithWarnings};
+use crate::project::Project;
+use crate::templates::{edit_project_instances_in, instance_count, instance_uid, InstanceContext, LayoutInstanceChanges};
+use crate::{read_json_file, RejectedInstance};
+
+#[derive(Deserialize, Debug)]
+pub struct ReplaceObjectTypePayload {
+    project_path: String,
+    from_type: String,
+    to_type: String,
+    /// Old instance variable name to the new type's, for variables that were renamed.
+    #[serde(default)]
+    variable_map: HashMap<String, String>,
+    /// Old behavior name to the new type's.
+    #[serde(default)]
+    behavior_map: HashMap<String, String>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// What an instance had that the new type has no place for.
+#[derive(Serialize, Debug, Clone)]
+pub struct LostInstanceData {
+    layout_path: String,
+    uid: u32,
+    instance_variables: Map<String, Value>,
+    behaviors: Map<String, Value>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReplaceObjectTypeResult {
+    dry_run: bool,
+    message: String,
+    layouts: Vec<LayoutInstanceChanges>,
+    lost: Vec<LostInstanceData>,
+    /// Templates and replicas, their template belongs to the old type.
+    skipped: Vec<RejectedInstance>,
+}
+
+/// Instance variables (name and initial value) and behavior names an instance of the type can have,
+/// its families' included.
+struct TypeMembers {
+    variables: Vec<(String, Value)>,
+    behaviors: Vec<String>,
+    plugin_id: Option<String>,
+}
+
+fn read_members(definition: &Value, members: &mut TypeMembers) {
+    if let Some(variables) = definition.get("instanceVariables").and_then(|v| v.as_array()) {
+        for variable in variables {
+            let Some(name) = variable.get("name").and_then(|n| n.as_str()) else { continue };
+            let initial_value = variable.get("initialValue").cloned().unwrap_or(Value::Null);
+            members.variables.push((name.to_string(), initial_value));
+        }
+    }
+    if let Some(behaviors) = definition.get("behaviorTypes").and_then(|b| b.as_array()) {
+        members.behaviors.extend(behaviors.iter().filter_map(|b| b.get("name").and_then(|n| n.as_str())).map(String::from));
+    }
+}
+
+fn type_members(project_base_path: &Path, project: &Project, object_type: &str, warnings: &mut Vec<AppError>) -> Result<TypeMembers, AppError> {
+    let path = project.object_type_path(object_type)
+        .ok_or_else(|| AppError::new(AppErrorKind::NotFound, format!("No object type named '{}'", object_type)))?;
+    let (definition, _) = read_json_file(&project_base_path.join(path))?;
+    let mut members = TypeMembers {
+        variables: Vec::new(),
+        behaviors: Vec::new(),
+        plugin_id: definition.get("plugin-id").and_then(|p| p.as_str()).map(String::from),
+    };
+    read_members(&definition, &mut members);
+    for family_path in project.family_paths() {
+        let family = match read_json_file(&project_base_path.join(&family_path)) {
+            Ok((family, _)) => family,
+            Err(e) => {
+                warnings.push(e);
+                continue;
+            }
+        };
+        let is_member = family.get("members").and_then(|m| m.as_array())
+            .is_some_and(|m| m.iter().any(|member| member.as_str() == Some(object_type)));
+        if is_member {
+            read_members(&family, &mut members);
+        }
+    }
+    Ok(members)
+}
+
+/// Types made from different plugins have different properties, swapping them would leave instances Construct can't load.
+fn check_same_plugin(payload: &ReplaceObjectTypePayload, from_members: &TypeMembers, to_members: &TypeMembers) -> Result<(), AppError> {
+    if let (Some(from_plugin), Some(to_plugin)) = (&from_members.plugin_id, &to_members.plugin_id) {
+        if from_plugin != to_plugin {
+            return Err(AppError::new(AppErrorKind::InvalidInput, format!(
+                "'{}' is a {} and '{}' a {}, their properties don't match",
+                payload.from_type, from_plugin, payload.to_type, to_plugin,
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Gives one instance of `from_type` the new type, recording what it loses in `lost`, or why it was left alone in `skipped`.
+fn replace_instance_type(
+    payload: &ReplaceObjectTypePayload,
+    to_members: &TypeMembers,
+    context: &InstanceContext,
+    instance: &mut Map<String, Value>,
+    lost: &mut Vec<LostInstanceData>,
+    skipped: &mut Vec<RejectedInstance>,
+) -> bool {
+    if instance.get("type").and_then(|t| t.as_str()) != Some(payload.from_type.as_str()) {
+        return false;
+    }
+    let uid = instance_uid(instance);
+    if let Some(mode) = instance.get("template").and_then(|t| t.get("mode")).and_then(|m| m.as_str()) {
+        if mode == "template" || mode == "replica" {
+            skipped.push(RejectedInstance {
+                layout_path: context.layout_path.to_string(),
+                uid,
+                reason: format!("Instance is a {}, its template belongs to '{}'", mode, payload.from_type),
+            });
+            return false;
+        }
+    }
+    instance.insert("type".to_string(), Value::String(payload.to_type.clone()));
+
+    let mut old_variables = match instance.get("instanceVariables") {
+        Some(Value::Object(variables)) => variables.clone(),
+        _ => Map::new(),
+    };
+    let mut new_variables = Map::new();
+    for (name, initial_value) in &to_members.variables {
+        let source = payload.variable_map.iter().find(|(_, to)| *to == name).map(|(from, _)| from.as_str()).unwrap_or(name);
+        let value = old_variables.get(source).cloned().unwrap_or_else(|| initial_value.clone());
+        old_variables.retain(|k, _| k != source);
+        new_variables.insert(name.clone(), value);
+    }
+    if instance.contains_key("instanceVariables") || !new_variables.is_empty() {
+        instance.insert("instanceVariables".to_string(), Value::Object(new_variables));
+    }
+
+    let mut lost_behaviors = Map::new();
+    if let Some(behaviors) = instance.get_mut("behaviors").and_then(|b| b.as_object_mut()) {
+        let mut new_behaviors = Map::new();
+        for (name, behavior) in std::mem::take(behaviors) {
+            let target = payload.behavior_map.get(&name).unwrap_or(&name);
+            if to_members.behaviors.contains(target) {
+                new_behaviors.insert(target.clone(), behavior);
+            } else {
+                lost_behaviors.insert(name, behavior);
+            }
+        }
+        *behaviors = new_behaviors;
+    }
+
+    if !old_variables.is_empty() || !lost_behaviors.is_empty() {
+        lost.push(LostInstanceData {
+            layout_path: context.layout_path.to_string(),
+            uid,
+            instance_variables: old_variables,
+            behaviors: lost_behaviors,
+        });
+    }
+    true
+}
+
+/// Swaps the type of every instance of `from_type` for `to_type`, keeping position, size and layer.
+/// Instance variables and behaviors are carried over by name (or through the maps), the ones the new
+/// type doesn't have are dropped and reported. Event sheets referring to the old type are left alone.
+#[tauri::command]
+pub fn replace_object_type(app: tauri::AppHandle, payload: ReplaceObjectTypePayload) -> Result<WithWarnings<ReplaceObjectTypeResult>, AppError> {
+    let project_base_path = PathBuf::from(&payload.project_path);
+    if payload.from_type == payload.to_type {
+        return Err(AppError::new(AppErrorKind::InvalidInput, "The old and new object types are the same"));
+    }
+    let project = Project::load(&project_base_path)?;
+    let mut warnings = Vec::new();
+    let from_members = type_members(&project_base_path, &project, &payload.from_type, &mut warnings)?;
+    let to_members = type_members(&project_base_path, &project, &payload.to_type, &mut warnings)?;
+    check_same_plugin(&payload, &from_members, &to_members)?;
+
+    let mut lost = Vec::new();
+    let mut skipped = Vec::new();
+    let edited = edit_project_instances_in(&app, &project_base_path, "replace_object_type", payload.dry_run, |context, instance| {
+        replace_instance_type(&payload, &to_members, context, instance, &mut lost, &mut skipped)
+    })?;
+    warnings.extend(edited.warnings);
+    let layouts = edited.value;
+    let message = if payload.dry_run {
+        format!("Dry run: {} instances of '{}' across {} layouts would become '{}', {} would lose data.", instance_count(&layouts), payload.from_type, layouts.len(), payload.to_type, lost.len())
+    } else {
+        format!("Replaced {} instances of '{}' with '{}' across {} layouts, {} lost data.", instance_count(&layouts), payload.from_type, payload.to_type, layouts.len(), lost.len())
+    };
+    Ok(WithWarnings { value: ReplaceObjectTypeResult { dry_run: payload.dry_run, message, layouts, lost, skipped }, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    const CONTEXT: InstanceContext = InstanceContext { layout_name: "Level", layout_path: "layouts/Level.json", layer_name: "Layer 0" };
+
+    /// `Enemy` and `Boss` are sprites, `Boss` gets a variable and a behavior from the `Foes` family; `Label` is a text.
+    fn project_members(name: &str) -> (PathBuf, HashMap<&'static str, TypeMembers>) {
+        let dir = std::env::temp_dir().join(format!("construct-crawler-test-object-types-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("objectTypes")).unwrap();
+        fs::create_dir_all(dir.join("families")).unwrap();
+        let project = json!({ "objectTypes": { "items": ["Enemy", "Boss", "Label"] }, "families": { "items": ["Foes"] } });
+        fs::write(dir.join("project.c3proj"), project.to_string()).unwrap();
+        for (file, definition) in [
+            ("objectTypes/Enemy.json", json!({
+                "plugin-id": "Sprite",
+                "instanceVariables": [{ "name": "health", "initialValue": 5 }, { "name": "loot", "initialValue": "" }],
+                "behaviorTypes": [{ "name": "Platform" }, { "name": "Fade" }]
+            })),
+            ("objectTypes/Boss.json", json!({
+                "plugin-id": "Sprite",
+                "instanceVariables": [{ "name": "hp", "initialValue": 100 }, { "name": "phase", "initialValue": 1 }],
+                "behaviorTypes": [{ "name": "Move" }]
+            })),
+            ("objectTypes/Label.json", json!({ "plugin-id": "Text" })),
+            ("families/Foes.json", json!({
+                "members": ["Boss"],
+                "instanceVariables": [{ "name": "team", "initialValue": "red" }],
+                "behaviorTypes": [{ "name": "Flash" }]
+            })),
+        ] {
+            fs::write(dir.join(file), definition.to_string()).unwrap();
+        }
+        let project = Project::load(&dir).unwrap();
+        let mut warnings = Vec::new();
+        let members = ["Enemy", "Boss", "Label"].into_iter()
+            .map(|name| (name, type_members(&dir, &project, name, &mut warnings).unwrap()))
+            .collect();
+        assert!(warnings.is_empty());
+        (dir, members)
+    }
+
+    fn payload(from_type: &str, to_type: &str) -> ReplaceObjectTypePayload {
+        serde_json::from_value(json!({
+            "project_path": "",
+            "from_type": from_type,
+            "to_type": to_type,
+            "variable_map": { "health": "hp" },
+            "behavior_map": { "Platform": "Move" }
+        })).unwrap()
+    }
+
+    fn object(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn replace_instance_type_maps_variables_and_behaviors_and_reports_the_rest() {
+        let (dir, members) = project_members("replace");
+        let mut instance = object(json!({
+            "type": "Enemy",
+            "uid": 4,
+            "world": { "x": 10, "y": 20 },
+            "instanceVariables": { "health": 3, "loot": "gem" },
+            "behaviors": { "Platform": { "properties": { "max-speed": 200 } }, "Fade": { "properties": {} } }
+        }));
+        let (mut lost, mut skipped) = (Vec::new(), Vec::new());
+        assert!(replace_instance_type(&payload("Enemy", "Boss"), &members["Boss"], &CONTEXT, &mut instance, &mut lost, &mut skipped));
+
+        assert_eq!(Value::Object(instance), json!({
+            "type": "Boss",
+            "uid": 4,
+            "world": { "x": 10, "y": 20 },
+            "instanceVariables": { "hp": 3, "phase": 1, "team": "red" },
+            "behaviors": { "Move": { "properties": { "max-speed": 200 } } }
+        }));
+        assert_eq!(lost.len(), 1);
+        assert_eq!((lost[0].layout_path.as_str(), lost[0].uid), ("layouts/Level.json", 4));
+        assert_eq!(Value::Object(lost[0].instance_variables.clone()), json!({ "loot": "gem" }));
+        assert_eq!(Value::Object(lost[0].behaviors.clone()), json!({ "Fade": { "properties": {} } }));
+        assert!(skipped.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replace_instance_type_skips_templates_replicas_and_other_types() {
+        let (dir, members) = project_members("skip");
+        let (mut lost, mut skipped) = (Vec::new(), Vec::new());
+        for instance in [
+            json!({ "type": "Enemy", "uid": 1, "template": { "mode": "template", "templateName": "grunt" } }),
+            json!({ "type": "Enemy", "uid": 2, "template": { "mode": "replica", "sourceTemplateName": "grunt" } }),
+            json!({ "type": "Label", "uid": 3 }),
+        ] {
+            let mut edited = object(instance.clone());
+            assert!(!replace_instance_type(&payload("Enemy", "Boss"), &members["Boss"], &CONTEXT, &mut edited, &mut lost, &mut skipped));
+            assert_eq!(Value::Object(edited), instance);
+        }
+        assert_eq!(skipped.iter().map(|s| s.uid).collect::<Vec<_>>(), [1, 2]);
+        assert!(lost.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn check_same_plugin_refuses_types_of_different_plugins() {
+        let (dir, members) = project_members("plugins");
+        assert!(check_same_plugin(&payload("Enemy", "Boss"), &members["Enemy"], &members["Boss"]).is_ok());
+        let error = check_same_plugin(&payload("Enemy", "Label"), &members["Enemy"], &members["Label"]).unwrap_err();
+        assert_eq!(error.kind, AppErrorKind::InvalidInput);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,295 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use serde::{Deserialize, Serialize};
+
+use crate::images::RgbaImage;
+
+/// Pixel coordinates, from the top left corner of the image.
+pub type Point = (f64, f64);
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PolygonShape {
+    /// Hull of every visible pixel.
+    Convex,
+    /// Outline of the largest visible area, holes and smaller islands left out.
+    Concave,
+}
+
+struct Mask {
+    width: usize,
+    height: usize,
+    solid: Vec<bool>,
+}
+
+impl Mask {
+    fn get(&self, x: i64, y: i64) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height && self.solid[y as usize * self.width + x as usize]
+    }
+}
+
+/// Outline of the pixels with an alpha above `threshold`, simplified down to `max_vertices`.
+/// `None` when there's no such pixel.
+pub fn trace_polygon(image: &RgbaImage, threshold: u8, max_vertices: usize, shape: PolygonShape) -> Option<Vec<Point>> {
+    let mask = Mask {
+        width: image.width as usize,
+        height: image.height as usize,
+        solid: image.pixels.chunks_exact(4).map(|p| p[3] > threshold).collect(),
+    };
+    let outline = match shape {
+        PolygonShape::Convex => convex_hull(row_corners(&mask)),
+        PolygonShape::Concave => {
+            let (area, start) = largest_area(&mask)?;
+            trace_outline(&area, start)
+        }
+    };
+    if outline.len() < 3 {
+        return None;
+    }
+    Some(simplify(outline, max_vertices))
+}
+
+/// Twice the signed area of the triangle, positive when `o`, `a`, `b` turn clockwise on screen.
+fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+/// Corners of the first and last solid pixel of every row, the hull of the mask is the hull of those.
+fn row_corners(mask: &Mask) -> Vec<Point> {
+    let mut corners = Vec::new();
+    for (y, row) in mask.solid.chunks_exact(mask.width).enumerate() {
+        let Some(first) = row.iter().position(|s| *s) else { continue };
+        let last = row.iter().rposition(|s| *s).unwrap_or(first) + 1;
+        let (top, bottom) = (y as f64, y as f64 + 1.0);
+        corners.extend([(first as f64, top), (first as f64, bottom), (last as f64, top), (last as f64, bottom)]);
+    }
+    corners
+}
+
+/// Andrew's monotone chain, collinear points dropped.
+fn convex_hull(mut points: Vec<Point>) -> Vec<Point> {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let half_hull = |points: &mut dyn Iterator<Item = &Point>| {
+        let mut hull: Vec<Point> = Vec::new();
+        for &point in points {
+            while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+        hull
+    };
+    let mut hull = half_hull(&mut points.iter());
+    hull.extend(half_hull(&mut points.iter().rev()));
+    hull
+}
+
+/// The largest 4-connected area of solid pixels alone in a mask, with the index of its first pixel in reading order.
+fn largest_area(mask: &Mask) -> Option<(Mask, usize)> {
+    let mut seen = vec![false; mask.solid.len()];
+    let mut largest: Vec<usize> = Vec::new();
+    let mut queue = VecDeque::new();
+    for start in 0..mask.solid.len() {
+        if !mask.solid[start] || seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        queue.push_back(start);
+        let mut area = Vec::new();
+        while let Some(index) = queue.pop_front() {
+            area.push(index);
+            let (x, y) = ((index % mask.width) as i64, (index / mask.width) as i64);
+            for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if !mask.get(nx, ny) {
+                    continue;
+                }
+                let neighbor = ny as usize * mask.width + nx as usize;
+                if !seen[neighbor] {
+                    seen[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        if area.len() > largest.len() {
+            largest = area;
+        }
+    }
+    let start = *largest.iter().min()?;
+    let mut solid = vec![false; mask.solid.len()];
+    largest.iter().for_each(|index| solid[*index] = true);
+    Some((Mask { width: mask.width, height: mask.height, solid }, start))
+}
+
+/// Which pixel lies half a step along `d` + `n` from a corner, `d` and `n` being a unit direction and its normal.
+fn pixel_offset(d: i64, n: i64) -> i64 {
+    if d + n > 0 { 0 } else { -1 }
+}
+
+/// Walks the pixel edges around the area starting at its top left corner, solid pixels on the right,
+/// and keeps the corners where it turns.
+fn trace_outline(mask: &Mask, start: usize) -> Vec<Point> {
+    let (start_x, start_y) = ((start % mask.width) as i64, (start / mask.width) as i64);
+    let (mut x, mut y, mut dx, mut dy) = (start_x, start_y, 1i64, 0i64);
+    let mut corners = Vec::new();
+    loop {
+        x += dx;
+        y += dy;
+        // Right hand side normal, y pointing down.
+        let (rx, ry) = (-dy, dx);
+        let ahead_right = mask.get(x + pixel_offset(dx, rx), y + pixel_offset(dy, ry));
+        let ahead_left = mask.get(x + pixel_offset(dx, -rx), y + pixel_offset(dy, -ry));
+        let (next_dx, next_dy) = if !ahead_right {
+            (rx, ry)
+        } else if ahead_left {
+            (-rx, -ry)
+        } else {
+            (dx, dy)
+        };
+        if (next_dx, next_dy) != (dx, dy) {
+            corners.push((x as f64, y as f64));
+        }
+        (dx, dy) = (next_dx, next_dy);
+        if (x, y, dx, dy) == (start_x, start_y, 1, 0) {
+            return corners;
+        }
+    }
+}
+
+/// Whether `p` lies in the triangle `a`, `b`, `c` or on its sides.
+fn in_triangle(p: Point, a: Point, b: Point, c: Point) -> bool {
+    let sides = [cross(a, b, p), cross(b, c, p), cross(c, a, p)];
+    let within_box = p.0 >= a.0.min(b.0).min(c.0) && p.0 <= a.0.max(b.0).max(c.0)
+        && p.1 >= a.1.min(b.1).min(c.1) && p.1 <= a.1.max(b.1).max(c.1);
+    within_box && (sides.iter().all(|s| *s >= 0.0) || sides.iter().all(|s| *s <= 0.0))
+}
+
+/// Visvalingam-Whyatt on a closed outline: drops the point making the smallest triangle with its neighbors
+/// until `max_vertices` are left. A point is kept while another one lies in its triangle, the edge replacing it
+/// would cross the outline. When only such points are left, the convex hull of the rest is simplified instead.
+fn simplify(points: Vec<Point>, max_vertices: usize) -> Vec<Point> {
+    let count = points.len();
+    if count <= max_vertices {
+        return points;
+    }
+    let mut previous: Vec<usize> = (0..count).map(|i| (i + count - 1) % count).collect();
+    let mut next: Vec<usize> = (0..count).map(|i| (i + 1) % count).collect();
+    // Areas are positive, so their bits order like they do.
+    let area = |i: usize, previous: &[usize], next: &[usize]| cross(points[previous[i]], points[i], points[next[i]]).abs().to_bits();
+    let mut areas: Vec<u64> = (0..count).map(|i| area(i, &previous, &next)).collect();
+    let mut removed = vec![false; count];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = areas.iter().enumerate().map(|(i, a)| Reverse((*a, i))).collect();
+    let mut left = count;
+    while left > max_vertices {
+        let Some(Reverse((smallest, i))) = heap.pop() else { break };
+        if removed[i] || smallest != areas[i] {
+            continue;
+        }
+        let (before, after) = (previous[i], next[i]);
+        let (a, b, c) = (points[before], points[i], points[after]);
+        // Points sharing an end of the new edge (outlines pinch where pixels touch diagonally) can't make it cross.
+        let blocked = std::iter::successors(Some(next[after]), |v| Some(next[*v]))
+            .take_while(|v| *v != before)
+            .any(|v| points[v] != a && points[v] != c && in_triangle(points[v], a, b, c));
+        if blocked {
+            // Tried again once a neighbor goes.
+            continue;
+        }
+        removed[i] = true;
+        left -= 1;
+        next[before] = after;
+        previous[after] = before;
+        for neighbor in [before, after] {
+            areas[neighbor] = area(neighbor, &previous, &next);
+            heap.push(Reverse((areas[neighbor], neighbor)));
+        }
+    }
+    let kept: Vec<Point> = points.iter().zip(removed).filter(|(_, removed)| !removed).map(|(point, _)| *point).collect();
+    if kept.len() > max_vertices {
+        return simplify(convex_hull(kept), max_vertices);
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One string per row, `#` for solid pixels.
+    fn mask(rows: &[&str]) -> Mask {
+        Mask {
+            width: rows[0].len(),
+            height: rows.len(),
+            solid: rows.iter().flat_map(|row| row.chars().map(|c| c == '#')).collect(),
+        }
+    }
+
+    fn outline(rows: &[&str]) -> Vec<Point> {
+        let (area, start) = largest_area(&mask(rows)).unwrap();
+        trace_outline(&area, start)
+    }
+
+    /// Whether two edges of the polygon cross, edges merely touching don't count: pixel outlines pinch where
+    /// pixels of the area touch diagonally.
+    fn crosses_itself(polygon: &[Point]) -> bool {
+        let n = polygon.len();
+        let side = |v: f64| if v > 0.0 { 1 } else if v < 0.0 { -1 } else { 0 };
+        (0..n).any(|i| (0..n).any(|j| {
+            let (a, b, c, d) = (polygon[i], polygon[(i + 1) % n], polygon[j], polygon[(j + 1) % n]);
+            side(cross(a, b, c)) * side(cross(a, b, d)) < 0 && side(cross(c, d, a)) * side(cross(c, d, b)) < 0
+        }))
+    }
+
+    #[test]
+    fn trace_outline_follows_pixel_edges() {
+        assert_eq!(outline(&["#"]), [(1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)]);
+        assert_eq!(outline(&["...", ".##", ".##"]), [(3.0, 1.0), (3.0, 3.0), (1.0, 3.0), (1.0, 1.0)]);
+        assert_eq!(outline(&["#..", "#..", "###"]), [(1.0, 0.0), (1.0, 2.0), (3.0, 2.0), (3.0, 3.0), (0.0, 3.0), (0.0, 0.0)]);
+    }
+
+    #[test]
+    fn largest_area_drops_islands() {
+        assert_eq!(outline(&["#.##", "..##"]), [(4.0, 0.0), (4.0, 2.0), (2.0, 2.0), (2.0, 0.0)]);
+    }
+
+    #[test]
+    fn convex_hull_drops_collinear_points() {
+        let points = vec![(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0), (0.0, 2.0), (0.0, 1.0), (1.0, 1.0)];
+        assert_eq!(convex_hull(points), [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]);
+    }
+
+    #[test]
+    fn simplify_respects_the_vertex_cap() {
+        let circle: Vec<Point> = (0..64).map(|i| {
+            let angle = i as f64 * std::f64::consts::TAU / 64.0;
+            (10.0 * angle.cos(), 10.0 * angle.sin())
+        }).collect();
+        let simplified = simplify(circle, 8);
+        assert_eq!(simplified.len(), 8);
+        assert!(!crosses_itself(&simplified));
+    }
+
+    #[test]
+    fn simplify_keeps_the_outline_simple() {
+        // Removing points by area alone makes this one cross itself at 10 vertices.
+        let outline = outline(&[
+            "##.###..",
+            "#####...",
+            "#.####..",
+            "##.#.##.",
+            "##...##.",
+            "#.#.####",
+            "#.####..",
+        ]);
+        assert!(!crosses_itself(&outline));
+        for max_vertices in 3..outline.len() {
+            let simplified = simplify(outline.clone(), max_vertices);
+            assert!(simplified.len() <= max_vertices, "{:?}", simplified);
+            assert!(!crosses_itself(&simplified), "{} vertices: {:?}", max_vertices, simplified);
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::AppError;
+use crate::{parse_layout_list_xml, LayoutEntry};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectFormat {
+    #[default]
+    Json,
+    Xml,
+}
+
+/// A folder of the c3proj tree. The root folders have an empty name.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProjectFolder<T> {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "Vec::new")]
+    pub items: Vec<T>,
+    #[serde(default = "Vec::new")]
+    pub subfolders: Vec<ProjectFolder<T>>,
+}
+
+impl<T> ProjectFolder<T> {
+    /// Every item of the tree along with the slash separated path of its folder ("" for the root).
+    pub fn items_with_paths(&self) -> Vec<(String, &T)> {
+        let mut result = Vec::new();
+        self.collect_items("", &mut result);
+        result
+    }
+
+    fn collect_items<'a>(&'a self, folder_path: &str, result: &mut Vec<(String, &'a T)>) {
+        for item in &self.items {
+            result.push((folder_path.to_string(), item));
+        }
+        for subfolder in &self.subfolders {
+            // Unnamed folders don't add a path segment, matching what the store used to do.
+            let subfolder_path = match (folder_path.is_empty(), subfolder.name.trim().is_empty()) {
+                (_, true) => folder_path.to_string(),
+                (true, false) => subfolder.name.clone(),
+                (false, false) => format!("{}/{}", folder_path, subfolder.name),
+            };
+            subfolder.collect_items(&subfolder_path, result);
+        }
+    }
+}
+
+/// An entry of one of the project file folders (scripts, sounds, fonts...).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProjectFile {
+    pub name: String,
+    #[serde(default, rename = "type")]
+    pub mime_type: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RootFileFolders {
+    #[serde(default, rename(deserialize = "script"))]
+    pub scripts: ProjectFolder<ProjectFile>,
+    #[serde(default, rename(deserialize = "sound"))]
+    pub sounds: ProjectFolder<ProjectFile>,
+    #[serde(default)]
+    pub music: ProjectFolder<ProjectFile>,
+    #[serde(default, rename(deserialize = "video"))]
+    pub videos: ProjectFolder<ProjectFile>,
+    #[serde(default, rename(deserialize = "font"))]
+    pub fonts: ProjectFolder<ProjectFile>,
+    #[serde(default, rename(deserialize = "general"))]
+    pub files: ProjectFolder<ProjectFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct Project {
+    #[serde(skip_deserializing)]
+    pub format: ProjectFormat,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub unique_id: String,
+    #[serde(default)]
+    pub project_format_version: u32,
+    #[serde(default)]
+    pub saved_with_release: u32,
+    #[serde(default)]
+    pub properties: Map<String, Value>,
+    #[serde(default)]
+    pub object_types: ProjectFolder<String>,
+    #[serde(default)]
+    pub families: ProjectFolder<String>,
+    #[serde(default)]
+    pub layouts: ProjectFolder<String>,
+    #[serde(default)]
+    pub event_sheets: ProjectFolder<String>,
+    #[serde(default)]
+    pub timelines: ProjectFolder<String>,
+    #[serde(default)]
+    pub root_file_folders: RootFileFolders,
+    /// Layout files resolved from the layouts tree, relative to the project folder.
+    #[serde(skip_deserializing)]
+    pub layout_entries: Vec<LayoutEntry>,
+    /// Event sheet files resolved the same way, empty for XML projects.
+    #[serde(skip_deserializing)]
+    pub event_sheet_entries: Vec<LayoutEntry>,
+}
+
+/// Files of a project folder tree stored as `<dir>/<folder path>/<name>.json`.
+fn folder_entries(folder: &ProjectFolder<String>, dir: &str) -> Vec<LayoutEntry> {
+    folder.items_with_paths().into_iter()
+        .map(|(folder_path, name)| LayoutEntry {
+            name: name.clone(),
+            path: if folder_path.is_empty() {
+                format!("{}/{}.json", dir, name)
+            } else {
+                format!("{}/{}/{}.json", dir, folder_path, name)
+            },
+        })
+        .collect()
+}
+
+impl Project {
+    pub fn from_json_str(content: &str) -> Result<Project, serde_json::Error> {
+        let mut project: Project = serde_json::from_str(content)?;
+        project.format = ProjectFormat::Json;
+        project.layout_entries = folder_entries(&project.layouts, "layouts");
+        project.event_sheet_entries = folder_entries(&project.event_sheets, "eventSheets");
+        Ok(project)
+    }
+
+    /// The XML format only carries the layout list, everything else is left empty.
+    pub fn from_xml_str(content: &str) -> Result<Project, AppError> {
+        let layout_entries = parse_layout_list_xml(content)?;
+        let mut project = Project { format: ProjectFormat::Xml, ..Default::default() };
+        project.layouts.items = layout_entries.iter().map(|l| l.name.clone()).collect();
+        project.layout_entries = layout_entries;
+        Ok(project)
+    }
+
+    /// JSON file of the object type, relative to the project folder.
+    pub fn object_type_path(&self, name: &str) -> Option<String> {
+        folder_entries(&self.object_types, "objectTypes").into_iter().find(|e| e.name == name).map(|e| e.path)
+    }
+
+    pub fn family_path(&self, name: &str) -> Option<String> {
+        folder_entries(&self.families, "families").into_iter().find(|e| e.name == name).map(|e| e.path)
+    }
+
+    pub fn family_paths(&self) -> Vec<String> {
+        folder_entries(&self.families, "families").into_iter().map(|e| e.path).collect()
+    }
+
+    pub fn load(project_dir: &Path) -> Result<Project, AppError> {
+        let c3proj_path = project_dir.join("project.c3proj");
+        if !c3proj_path.exists() {
+            return Err(AppError::not_found(&c3proj_path));
+        }
+        let content = fs::read_to_string(&c3proj_path).map_err(|e| AppError::io(&c3proj_path, e))?;
+        let mut project = if content.trim_start().starts_with('<') {
+            Project::from_xml_str(&content).map_err(|e| e.with_file(&c3proj_path))?
+        } else {
+            Project::from_json_str(&content).map_err(|e| AppError::json(&c3proj_path, e))?
+        };
+        if project.name.is_empty() {
+            project.name = project_dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        }
+        Ok(project)
+    }
+}
+
+#[tauri::command]
+pub fn load_project(project_path: String) -> Result<Project, AppError> {
+    Project::load(Path::new(&project_path))
+}
@@ -0,0 +1,181 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{AppError, AppErrorKind};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotFile {
+    /// Path relative to the project folder, with `/` separators.
+    path: String,
+    /// False when the command created the file, restoring then deletes it.
+    existed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotManifest {
+    id: String,
+    project_path: String,
+    command: String,
+    created_at: u64,
+    files: Vec<SnapshotFile>,
+}
+
+pub fn app_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    app.path_resolver().app_data_dir()
+        .ok_or_else(|| AppError::internal("Could not resolve the app data folder"))
+}
+
+/// Stable FNV-1a hash, so folder names don't change across app versions.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+pub fn project_key(project_dir: &Path) -> String {
+    format!("{:016x}", fnv1a(project_dir.to_string_lossy().as_bytes()))
+}
+
+/// SHA-256, files with the same hash are taken as having the same content.
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// `content_hash` of a file, without loading it whole.
+pub fn file_hash(path: &Path) -> Result<String, AppError> {
+    let mut file = fs::File::open(path).map_err(|e| AppError::io(path, e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| AppError::io(path, e))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn snapshots_dir(data_dir: &Path, project_dir: &Path) -> PathBuf {
+    data_dir.join("snapshots").join(project_key(project_dir))
+}
+
+pub fn relative_project_path(project_dir: &Path, file: &Path) -> Result<String, AppError> {
+    let relative = file.strip_prefix(project_dir).map_err(|_| {
+        AppError::new(AppErrorKind::Internal, "File is outside of the project folder").with_file(file)
+    })?;
+    Ok(relative.components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// Copies `files` (absolute paths inside `project_dir`) into a new snapshot folder.
+pub fn create_snapshot(data_dir: &Path, project_dir: &Path, command: &str, files: &[PathBuf]) -> Result<SnapshotManifest, AppError> {
+    let created_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    let base_dir = snapshots_dir(data_dir, project_dir);
+    let mut id = format!("{}-{}", created_at, command);
+    let mut suffix = 1;
+    while base_dir.join(&id).exists() {
+        suffix += 1;
+        id = format!("{}-{}-{}", created_at, command, suffix);
+    }
+    let snapshot_dir = base_dir.join(&id);
+    let files_dir = snapshot_dir.join("files");
+    fs::create_dir_all(&files_dir).map_err(|e| AppError::io(&files_dir, e))?;
+
+    let mut manifest_files = Vec::new();
+    for file in files {
+        let relative = relative_project_path(project_dir, file)?;
+        if manifest_files.iter().any(|f: &SnapshotFile| f.path == relative) {
+            continue;
+        }
+        let existed = file.is_file();
+        if existed {
+            let target = files_dir.join(&relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| AppError::io(parent, e))?;
+            }
+            fs::copy(file, &target).map_err(|e| AppError::io(file, e))?;
+        }
+        manifest_files.push(SnapshotFile { path: relative, existed });
+    }
+
+    let manifest = SnapshotManifest {
+        id,
+        project_path: project_dir.to_string_lossy().into_owned(),
+        command: command.to_string(),
+        created_at,
+        files: manifest_files,
+    };
+    let manifest_path = snapshot_dir.join(MANIFEST_FILE);
+    let content = serde_json::to_string_pretty(&manifest).map_err(|e| AppError::json(&manifest_path, e))?;
+    fs::write(&manifest_path, content).map_err(|e| AppError::io(&manifest_path, e))?;
+    Ok(manifest)
+}
+
+pub fn read_snapshots(data_dir: &Path, project_dir: &Path) -> Result<Vec<SnapshotManifest>, AppError> {
+    let base_dir = snapshots_dir(data_dir, project_dir);
+    let mut snapshots = Vec::new();
+    if !base_dir.exists() {
+        return Ok(snapshots);
+    }
+    for entry in fs::read_dir(&base_dir).map_err(|e| AppError::io(&base_dir, e))? {
+        let entry = entry.map_err(|e| AppError::io(&base_dir, e))?;
+        let manifest_path = entry.path().join(MANIFEST_FILE);
+        // Folders without a manifest are snapshots that failed half way.
+        if let Ok(content) = fs::read_to_string(&manifest_path) {
+            let manifest: SnapshotManifest = serde_json::from_str(&content).map_err(|e| AppError::json(&manifest_path, e))?;
+            snapshots.push(manifest);
+        }
+    }
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+    Ok(snapshots)
+}
+
+/// Deletes every snapshot of a project.
+pub fn clear(data_dir: &Path, project_dir: &Path) -> Result<(), AppError> {
+    let base_dir = snapshots_dir(data_dir, project_dir);
+    match fs::remove_dir_all(&base_dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AppError::io(&base_dir, e)),
+        _ => Ok(()),
+    }
+}
+
+/// Puts every file of the snapshot back, after snapshotting their current state so a restore can be rolled back too.
+pub fn restore(data_dir: &Path, project_dir: &Path, snapshot_id: &str) -> Result<SnapshotManifest, AppError> {
+    if snapshot_id.is_empty() || snapshot_id.contains(['/', '\\']) || snapshot_id.contains("..") {
+        return Err(AppError::internal(format!("Invalid snapshot id '{}'", snapshot_id)));
+    }
+    let snapshot_dir = snapshots_dir(data_dir, project_dir).join(snapshot_id);
+    let manifest_path = snapshot_dir.join(MANIFEST_FILE);
+    let content = fs::read_to_string(&manifest_path).map_err(|e| AppError::io(&manifest_path, e))?;
+    let manifest: SnapshotManifest = serde_json::from_str(&content).map_err(|e| AppError::json(&manifest_path, e))?;
+
+    let targets: Vec<PathBuf> = manifest.files.iter().map(|f| project_dir.join(&f.path)).collect();
+    create_snapshot(data_dir, project_dir, "restore_snapshot", &targets)?;
+
+    for (file, target) in manifest.files.iter().zip(targets) {
+        if file.existed {
+            let source = snapshot_dir.join("files").join(&file.path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| AppError::io(parent, e))?;
+            }
+            fs::copy(&source, &target).map_err(|e| AppError::io(&source, e))?;
+        } else if target.exists() {
+            fs::remove_file(&target).map_err(|e| AppError::io(&target, e))?;
+        }
+    }
+    Ok(manifest)
+}
+
+#[tauri::command]
+pub fn list_snapshots(app: tauri::AppHandle, project_path: String) -> Result<Vec<SnapshotManifest>, AppError> {
+    read_snapshots(&app_data_dir(&app)?, Path::new(&project_path))
+}
+
+#[tauri::command]
+pub fn restore_snapshot(app: tauri::AppHandle, project_path: String, snapshot_id: String) -> Result<SnapshotManifest, AppError> {
+    restore(&app_data_dir(&app)?, Path::new(&project_path), &snapshot_id)
+}
@@ -0,0 +1,892 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::changes::{json_diff, ChangeSet, JsonChange};
+use crate::error::{AppError, AppErrorKind, WithWarnings};
+use crate::project::Project;
+use crate::{find_project_templates, read_json_file, snapshot, TemplateDefinition};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncProperty {
+    InstanceVariables,
+    Behaviors,
+    Effects,
+    Size,
+    Angle,
+    Opacity,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SyncReplicasPayload {
+    project_path: String,
+    template: TemplateDefinition,
+    properties: Vec<SyncProperty>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TemplatePayload {
+    project_path: String,
+    template: TemplateDefinition,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RenameTemplatePayload {
+    project_path: String,
+    template: TemplateDefinition,
+    new_name: String,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PromoteInstancePayload {
+    project_path: String,
+    layout_file_path: String,
+    instance_uid: u32,
+    template_name: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct InstanceChange {
+    uid: u32,
+    /// Changes to the instance object, pointers are relative to it.
+    diff: Vec<JsonChange>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LayoutInstanceChanges {
+    layout_path: String,
+    instances: Vec<InstanceChange>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct InstanceEditResult {
+    dry_run: bool,
+    message: String,
+    layouts: Vec<LayoutInstanceChanges>,
+}
+
+impl InstanceEditResult {
+    pub fn new(dry_run: bool, message: String, layouts: Vec<LayoutInstanceChanges>) -> Self {
+        InstanceEditResult { dry_run, message, layouts }
+    }
+}
+
+/// Every layer of the layout with its JSON pointer, depth first with each layer before its sub-layers,
+/// which is the order Construct numbers them in.
+pub fn layout_layers(layout_data: &Value) -> Vec<(String, &Map<String, Value>)> {
+    fn collect<'a>(layers: &'a [Value], pointer: &str, result: &mut Vec<(String, &'a Map<String, Value>)>) {
+        for (layer_index, layer) in layers.iter().enumerate() {
+            let layer_pointer = format!("{}/{}", pointer, layer_index);
+            if let Some(layer_obj) = layer.as_object() {
+                result.push((layer_pointer.clone(), layer_obj));
+            }
+            if let Some(sub_layers) = layer.get("subLayers").and_then(|l| l.as_array()) {
+                collect(sub_layers, &format!("{}/subLayers", layer_pointer), result);
+            }
+        }
+    }
+    let mut result = Vec::new();
+    if let Some(layers) = layout_data.get("layers").and_then(|l| l.as_array()) {
+        collect(layers, "/layers", &mut result);
+    }
+    result
+}
+
+/// Every instance object of the layout, sub-layers included, along with its JSON pointer.
+pub fn layout_instances(layout_data: &Value) -> Vec<(String, &Map<String, Value>)> {
+    fn collect<'a>(layers: &'a [Value], pointer: &str, result: &mut Vec<(String, &'a Map<String, Value>)>) {
+        for (layer_index, layer) in layers.iter().enumerate() {
+            let layer_pointer = format!("{}/{}", pointer, layer_index);
+            if let Some(instances) = layer.get("instances").and_then(|i| i.as_array()) {
+                for (instance_index, instance) in instances.iter().enumerate() {
+                    if let Some(instance_obj) = instance.as_object() {
+                        result.push((format!("{}/instances/{}", layer_pointer, instance_index), instance_obj));
+                    }
+                }
+            }
+            if let Some(sub_layers) = layer.get("subLayers").and_then(|l| l.as_array()) {
+                collect(sub_layers, &format!("{}/subLayers", layer_pointer), result);
+            }
+        }
+    }
+    let mut result = Vec::new();
+    if let Some(layers) = layout_data.get("layers").and_then(|l| l.as_array()) {
+        collect(layers, "/layers", &mut result);
+    }
+    result
+}
+
+/// `layout_instances`, mutably.
+pub fn layout_instances_mut(layout_data: &mut Value) -> Vec<(String, &mut Map<String, Value>)> {
+    fn collect<'a>(layers: &'a mut [Value], pointer: &str, result: &mut Vec<(String, &'a mut Map<String, Value>)>) {
+        for (layer_index, layer) in layers.iter_mut().enumerate() {
+            let layer_pointer = format!("{}/{}", pointer, layer_index);
+            if let Some(layer_obj) = layer.as_object_mut() {
+                // Split the borrow so instances and sub-layers can be walked at the same time.
+                let mut instances = None;
+                let mut sub_layers = None;
+                for (key, value) in layer_obj.iter_mut() {
+                    match key.as_str() {
+                        "instances" => instances = value.as_array_mut(),
+                        "subLayers" => sub_layers = value.as_array_mut(),
+                        _ => {}
+                    }
+                }
+                if let Some(instances) = instances {
+                    for (instance_index, instance) in instances.iter_mut().enumerate() {
+                        if let Some(instance_obj) = instance.as_object_mut() {
+                            result.push((format!("{}/instances/{}", layer_pointer, instance_index), instance_obj));
+                        }
+                    }
+                }
+                if let Some(sub_layers) = sub_layers {
+                    collect(sub_layers, &format!("{}/subLayers", layer_pointer), result);
+                }
+            }
+        }
+    }
+    let mut result = Vec::new();
+    if let Some(layers) = layout_data.get_mut("layers").and_then(|l| l.as_array_mut()) {
+        collect(layers, "/layers", &mut result);
+    }
+    result
+}
+
+pub fn instance_uid(instance: &Map<String, Value>) -> u32 {
+    instance.get("uid").and_then(|v| v.as_u64()).unwrap_or(0) as u32
+}
+
+fn template_field<'a>(instance: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    instance.get("template").and_then(|t| t.get(key)).and_then(|v| v.as_str())
+}
+
+pub fn is_template_instance(instance: &Map<String, Value>, template: &TemplateDefinition) -> bool {
+    instance.get("type").and_then(|t| t.as_str()) == Some(template.object_type.as_str())
+        && template_field(instance, "mode") == Some("template")
+        && template_field(instance, "templateName") == Some(template.name.as_str())
+}
+
+pub fn is_replica_of(instance: &Map<String, Value>, template: &TemplateDefinition) -> bool {
+    instance.get("type").and_then(|t| t.as_str()) == Some(template.object_type.as_str())
+        && template_field(instance, "mode") == Some("replica")
+        && template_field(instance, "sourceTemplateName") == Some(template.name.as_str())
+}
+
+/// UIDs of the scene graph children, read from the parent's `children` array
+/// whose entries are either a UID or an object with a `uid`.
+pub fn instance_children(instance: &Map<String, Value>) -> Vec<u32> {
+    instance.get("children").and_then(|c| c.as_array())
+        .map(|children| children.iter()
+            .filter_map(|child| child.as_u64().or_else(|| child.get("uid").and_then(|u| u.as_u64())))
+            .map(|uid| uid as u32)
+            .collect())
+        .unwrap_or_default()
+}
+
+/// A template instance and, recursively, its children.
+#[derive(Debug, Clone)]
+pub struct TemplateNode {
+    uid: u32,
+    /// Template name replicas of this node point at. Children that aren't templates themselves use their closest template ancestor's.
+    name: String,
+    object_type: String,
+    children: Vec<TemplateNode>,
+}
+
+impl TemplateNode {
+    fn build(instance: &Map<String, Value>, inherited_name: &str, by_uid: &HashMap<u32, &Map<String, Value>>, depth: usize) -> TemplateNode {
+        let name = match template_field(instance, "mode") {
+            Some("template") => template_field(instance, "templateName").unwrap_or(inherited_name),
+            _ => inherited_name,
+        };
+        // The depth guard only matters for broken files where an instance ends up its own ancestor.
+        let children = if depth < 64 {
+            instance_children(instance).iter()
+                .filter_map(|uid| by_uid.get(uid))
+                .map(|child| TemplateNode::build(child, name, by_uid, depth + 1))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        TemplateNode {
+            uid: instance_uid(instance),
+            name: name.to_string(),
+            object_type: instance.get("type").and_then(|t| t.as_str()).unwrap_or_default().to_string(),
+            children,
+        }
+    }
+}
+
+pub fn index_by_uid(layout_data: &Value) -> HashMap<u32, &Map<String, Value>> {
+    layout_instances(layout_data).into_iter()
+        .map(|(_, instance)| (instance_uid(instance), instance))
+        .collect()
+}
+
+/// The tree of `template` in its layout, `None` when the template instance isn't there.
+fn layout_template_tree(layout_data: &Value, template: &TemplateDefinition) -> Option<TemplateNode> {
+    let by_uid = index_by_uid(layout_data);
+    let root = by_uid.values().find(|instance| is_template_instance(instance, template))?;
+    Some(TemplateNode::build(root, &template.name, &by_uid, 0))
+}
+
+pub fn template_tree(project_path: &Path, template: &TemplateDefinition) -> Result<TemplateNode, AppError> {
+    let layout_path = project_path.join(&template.defined_in_layout_path);
+    let (layout_data, _) = read_json_file(&layout_path)?;
+    layout_template_tree(&layout_data, template)
+        .ok_or_else(|| AppError::new(AppErrorKind::NotFound, format!("Template '{}' of '{}' was not found", template.name, template.object_type))
+            .with_file(&layout_path))
+}
+
+/// Instances of a template across the project, keyed by layout path and UID: the template instance, its replicas,
+/// and the descendants of those replicas standing for the template's children. The descendants have their own object
+/// type, for which a template of the same name may also exist, so they are found by walking down from the replicas.
+pub struct TemplateMembers {
+    /// UID of the instance each member copies in the template's layout.
+    counterparts: HashMap<(String, u32), u32>,
+    /// The template instance and its children, by UID.
+    template_instances: HashMap<u32, Map<String, Value>>,
+}
+
+fn collect_template_instances(by_uid: &HashMap<u32, &Map<String, Value>>, node: &TemplateNode, template_name: &str, instances: &mut HashMap<u32, Map<String, Value>>) {
+    if node.name != template_name {
+        // A nested template, its replicas point at its own name.
+        return;
+    }
+    if let Some(instance) = by_uid.get(&node.uid) {
+        instances.insert(node.uid, (*instance).clone());
+    }
+    node.children.iter().for_each(|child| collect_template_instances(by_uid, child, template_name, instances));
+}
+
+/// Follows `plan_replica_hierarchy`: children are paired with the template's by object type and template name, in order.
+fn collect_replica(
+    by_uid: &HashMap<u32, &Map<String, Value>>,
+    uid: u32,
+    node: &TemplateNode,
+    template_name: &str,
+    layout_path: &str,
+    counterparts: &mut HashMap<(String, u32), u32>,
+) {
+    if node.name != template_name {
+        return;
+    }
+    counterparts.insert((layout_path.to_string(), uid), node.uid);
+    let Some(instance) = by_uid.get(&uid) else { return };
+    let mut unmatched_nodes: Vec<&TemplateNode> = node.children.iter().collect();
+    for child_uid in instance_children(instance) {
+        let Some(child) = by_uid.get(&child_uid) else { continue };
+        if template_field(child, "mode") != Some("replica") {
+            continue;
+        }
+        let child_type = child.get("type").and_then(|t| t.as_str()).unwrap_or_default();
+        let source = template_field(child, "sourceTemplateName");
+        let Some(position) = unmatched_nodes.iter().position(|n| n.object_type == child_type && source == Some(n.name.as_str())) else { continue };
+        let child_node = unmatched_nodes.remove(position);
+        // Terminates even on cyclic layouts, each call goes one level deeper in the finite template tree.
+        collect_replica(by_uid, child_uid, child_node, template_name, layout_path, counterparts);
+    }
+}
+
+impl TemplateMembers {
+    /// `layouts` are every layout of the project, by path.
+    pub fn build<'a>(template: &TemplateDefinition, tree: &TemplateNode, layouts: impl IntoIterator<Item = (&'a str, &'a Value)>) -> Self {
+        let mut members = TemplateMembers { counterparts: HashMap::new(), template_instances: HashMap::new() };
+        for (layout_path, layout_data) in layouts {
+            let by_uid = index_by_uid(layout_data);
+            if layout_path == template.defined_in_layout_path {
+                collect_template_instances(&by_uid, tree, &template.name, &mut members.template_instances);
+            }
+            for (uid, instance) in &by_uid {
+                if is_template_instance(instance, template) {
+                    members.counterparts.insert((layout_path.to_string(), *uid), *uid);
+                } else if is_replica_of(instance, template) {
+                    collect_replica(&by_uid, *uid, tree, &template.name, layout_path, &mut members.counterparts);
+                }
+            }
+        }
+        members
+    }
+
+    fn counterpart(&self, context: &InstanceContext, instance: &Map<String, Value>) -> Option<u32> {
+        self.counterparts.get(&(context.layout_path.to_string(), instance_uid(instance))).copied()
+    }
+
+    fn is_replica(&self, context: &InstanceContext, instance: &Map<String, Value>) -> bool {
+        template_field(instance, "mode") == Some("replica") && self.counterpart(context, instance).is_some()
+    }
+
+    fn is_template(&self, context: &InstanceContext, instance: &Map<String, Value>) -> bool {
+        template_field(instance, "mode") == Some("template") && self.counterpart(context, instance).is_some()
+    }
+
+    /// Copies `properties` from the template instance a replica stands for.
+    fn sync(&self, context: &InstanceContext, instance: &mut Map<String, Value>, properties: &[SyncProperty]) -> bool {
+        if !self.is_replica(context, instance) {
+            return false;
+        }
+        let Some(template_instance) = self.counterpart(context, instance).and_then(|uid| self.template_instances.get(&uid)) else { return false };
+        sync_instance(template_instance, instance, properties);
+        true
+    }
+
+    fn detach(&self, context: &InstanceContext, instance: &mut Map<String, Value>) -> bool {
+        if !self.is_replica(context, instance) {
+            return false;
+        }
+        clear_template(instance);
+        true
+    }
+
+    /// Demotes the template instance and detaches the replicas.
+    fn retire(&self, context: &InstanceContext, instance: &mut Map<String, Value>) -> bool {
+        if !self.is_template(context, instance) && !self.is_replica(context, instance) {
+            return false;
+        }
+        clear_template(instance);
+        true
+    }
+
+    fn rename(&self, context: &InstanceContext, instance: &mut Map<String, Value>, new_name: &str) -> bool {
+        if self.is_template(context, instance) {
+            set_template_field(instance, "templateName", new_name);
+        } else if self.is_replica(context, instance) {
+            set_template_field(instance, "sourceTemplateName", new_name);
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+/// Reads every layout to find the members of `template`. Layouts that can't be read are skipped,
+/// the edit that follows reports them.
+fn project_template_members(project_base_path: &Path, template: &TemplateDefinition, tree: &TemplateNode) -> Result<TemplateMembers, AppError> {
+    let project = Project::load(project_base_path)?;
+    let layouts: Vec<(&str, Value)> = project.layout_entries.iter()
+        .filter_map(|entry| read_json_file(&project_base_path.join(&entry.path)).ok().map(|(data, _)| (entry.path.as_str(), data)))
+        .collect();
+    Ok(TemplateMembers::build(template, tree, layouts.iter().map(|(path, data)| (*path, data))))
+}
+
+/// The tree of a template that may be gone already, in which case only replicas of the root can be found.
+fn template_tree_or_root(project_path: &Path, template: &TemplateDefinition) -> Result<TemplateNode, AppError> {
+    match template_tree(project_path, template) {
+        Err(e) if e.kind == AppErrorKind::NotFound => Ok(TemplateNode {
+            uid: 0,
+            name: template.name.clone(),
+            object_type: template.object_type.clone(),
+            children: Vec::new(),
+        }),
+        result => result,
+    }
+}
+
+/// Pairs the children of `root_uid` with the children of `tree`, matching object types in order, and records
+/// the template name each descendant should become a replica of. Children with no counterpart are rejected.
+pub fn plan_replica_hierarchy(
+    by_uid: &HashMap<u32, &Map<String, Value>>,
+    root_uid: u32,
+    tree: &TemplateNode,
+    assignments: &mut HashMap<u32, String>,
+    rejected: &mut Vec<(u32, String)>,
+) {
+    let Some(root) = by_uid.get(&root_uid) else { return };
+    let mut unmatched_nodes: Vec<&TemplateNode> = tree.children.iter().collect();
+    for child_uid in instance_children(root) {
+        let Some(child) = by_uid.get(&child_uid) else { continue };
+        let child_type = child.get("type").and_then(|t| t.as_str()).unwrap_or_default();
+        if template_field(child, "mode") == Some("template") {
+            rejected.push((child_uid, format!("Child of {} is a template itself", root_uid)));
+            continue;
+        }
+        match unmatched_nodes.iter().position(|node| node.object_type == child_type) {
+            Some(position) => {
+                let node = unmatched_nodes.remove(position);
+                assignments.insert(child_uid, node.name.clone());
+                // Terminates even on cyclic layouts, each call goes one level deeper in the finite template tree.
+                plan_replica_hierarchy(by_uid, child_uid, node, assignments, rejected);
+            }
+            None => rejected.push((child_uid, format!("Child of {} has no matching '{}' child in template '{}'", root_uid, child_type, tree.name))),
+        }
+    }
+}
+
+/// Sets `key` on `target` to the value it has on `source`, or removes it when `source` doesn't have one.
+fn copy_key(source: &Map<String, Value>, target: &mut Map<String, Value>, key: &str) {
+    match source.get(key) {
+        Some(value) => {
+            target.insert(key.to_string(), value.clone());
+        }
+        // `remove` would swap the last key into its place and reorder the file.
+        None => target.retain(|k, _| k != key),
+    }
+}
+
+/// Turns a template or replica back into a plain instance.
+fn clear_template(instance: &mut Map<String, Value>) {
+    instance.retain(|k, _| k != "template");
+}
+
+/// Keeps existing values of the other `template` properties, or adds Construct's defaults.
+pub fn insert_template_defaults(template_obj: &mut Map<String, Value>) {
+    template_obj.entry("replicaHierarchyInSyncWithTemplate".to_string()).or_insert(Value::Bool(true));
+    template_obj.entry("templatePropagateHierarchyChanges".to_string()).or_insert(Value::Bool(true));
+    template_obj.entry("replicaIgnoreTemplateHierarchyChanges".to_string()).or_insert(Value::Bool(false));
+    template_obj.entry("components".to_string()).or_insert_with(|| Value::Array(vec![]));
+}
+
+fn set_template_field(instance: &mut Map<String, Value>, key: &str, value: &str) {
+    if let Some(template_obj) = instance.get_mut("template").and_then(|t| t.as_object_mut()) {
+        template_obj.insert(key.to_string(), Value::String(value.to_string()));
+    }
+}
+
+fn world_mut(instance: &mut Map<String, Value>) -> Option<&mut Map<String, Value>> {
+    instance.get_mut("world").and_then(|w| w.as_object_mut())
+}
+
+fn sync_instance(template_instance: &Map<String, Value>, replica: &mut Map<String, Value>, properties: &[SyncProperty]) {
+    let empty = Map::new();
+    let template_world = template_instance.get("world").and_then(|w| w.as_object()).unwrap_or(&empty);
+    for property in properties {
+        match property {
+            SyncProperty::InstanceVariables => copy_key(template_instance, replica, "instanceVariables"),
+            SyncProperty::Behaviors => copy_key(template_instance, replica, "behaviors"),
+            SyncProperty::Effects => copy_key(template_instance, replica, "effects"),
+            SyncProperty::Size => {
+                if let Some(world) = world_mut(replica) {
+                    copy_key(template_world, world, "width");
+                    copy_key(template_world, world, "height");
+                }
+            }
+            SyncProperty::Angle => {
+                if let Some(world) = world_mut(replica) {
+                    copy_key(template_world, world, "angle");
+                }
+            }
+            SyncProperty::Opacity => {
+                if let Some(world) = world_mut(replica) {
+                    // Opacity is the alpha of the world color.
+                    let alpha = template_world.get("color").and_then(|c| c.get(3));
+                    if let (Some(alpha), Some(slot)) = (alpha, world.get_mut("color").and_then(|c| c.get_mut(3))) {
+                        *slot = alpha.clone();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Where an instance handed to `edit_project_instances_in` lives.
+pub struct InstanceContext<'a> {
+    pub layout_name: &'a str,
+    pub layout_path: &'a str,
+    pub layer_name: &'a str,
+}
+
+/// Runs `edit` on every instance of every layout of the project and writes the layouts it changed
+/// in one operation, unless `dry_run`. `edit` is told which layout and layer the instance is on,
+/// and returns whether it touched the instance.
+pub fn edit_project_instances_in(
+    app: &tauri::AppHandle,
+    project_base_path: &Path,
+    command: &str,
+    dry_run: bool,
+    edit: impl FnMut(&InstanceContext, &mut Map<String, Value>) -> bool,
+) -> Result<WithWarnings<Vec<LayoutInstanceChanges>>, AppError> {
+    edit_layout_instances(app, project_base_path, command, dry_run, false, edit)
+}
+
+/// `edit_project_instances_in` for edits that must reach every layout: one that can't be read is an error
+/// and nothing is written.
+fn edit_every_project_instance_in(
+    app: &tauri::AppHandle,
+    project_base_path: &Path,
+    command: &str,
+    dry_run: bool,
+    edit: impl FnMut(&InstanceContext, &mut Map<String, Value>) -> bool,
+) -> Result<Vec<LayoutInstanceChanges>, AppError> {
+    edit_layout_instances(app, project_base_path, command, dry_run, true, edit).map(|edited| edited.value)
+}
+
+fn edit_layout_instances(
+    app: &tauri::AppHandle,
+    project_base_path: &Path,
+    command: &str,
+    dry_run: bool,
+    strict: bool,
+    mut edit: impl FnMut(&InstanceContext, &mut Map<String, Value>) -> bool,
+) -> Result<WithWarnings<Vec<LayoutInstanceChanges>>, AppError> {
+    let project = Project::load(project_base_path)?;
+    let mut changes = ChangeSet::new(project_base_path, command);
+    let mut layouts = Vec::new();
+    let mut warnings = Vec::new();
+
+    for layout_entry in &project.layout_entries {
+        let full_layout_path = project_base_path.join(&layout_entry.path);
+        let mut layout_data = match read_json_file(&full_layout_path) {
+            Ok((d, _)) => d,
+            // Nothing was committed yet.
+            Err(e) if strict => return Err(e),
+            Err(e) => {
+                warnings.push(e);
+                continue;
+            }
+        };
+        let layer_names: HashMap<String, String> = layout_layers(&layout_data).into_iter()
+            .map(|(pointer, layer)| (pointer, layer.get("name").and_then(|n| n.as_str()).unwrap_or_default().to_string()))
+            .collect();
+
+        let mut instance_changes = Vec::new();
+        for (pointer, instance) in layout_instances_mut(&mut layout_data) {
+            let layer_pointer = pointer.rsplit_once("/instances/").map(|(layer, _)| layer).unwrap_or_default();
+            let context = InstanceContext {
+                layout_name: &layout_entry.name,
+                layout_path: &layout_entry.path,
+                layer_name: layer_names.get(layer_pointer).map(String::as_str).unwrap_or_default(),
+            };
+            let before = Value::Object(instance.clone());
+            if !edit(&context, instance) {
+                continue;
+            }
+            let diff = json_diff(Some(&before), Some(&Value::Object(instance.clone())));
+            if !diff.is_empty() {
+                instance_changes.push(InstanceChange { uid: instance_uid(instance), diff });
+            }
+        }
+
+        if !instance_changes.is_empty() {
+            if !dry_run {
+                changes.write_json(full_layout_path, &layout_data)?;
+            }
+            layouts.push(LayoutInstanceChanges { layout_path: layout_entry.path.clone(), instances: instance_changes });
+        }
+    }
+    if !dry_run {
+        changes.commit(&snapshot::app_data_dir(app)?)?;
+    }
+    Ok(WithWarnings { value: layouts, warnings })
+}
+
+pub fn instance_count(layouts: &[LayoutInstanceChanges]) -> usize {
+    layouts.iter().map(|l| l.instances.len()).sum()
+}
+
+/// Replicas of the template's children are synced with the matching child.
+#[tauri::command]
+pub fn sync_replicas_with_template(app: tauri::AppHandle, payload: SyncReplicasPayload) -> Result<WithWarnings<InstanceEditResult>, AppError> {
+    let project_base_path = PathBuf::from(&payload.project_path);
+    let tree = template_tree(&project_base_path, &payload.template)?;
+    let members = project_template_members(&project_base_path, &payload.template, &tree)?;
+    let edited = edit_project_instances_in(&app, &project_base_path, "sync_replicas_with_template", payload.dry_run, |context, instance| {
+        members.sync(context, instance, &payload.properties)
+    })?;
+    let layouts = edited.value;
+    let message = if payload.dry_run {
+        format!("Dry run: {} replicas of '{}' across {} layouts would be synced.", instance_count(&layouts), payload.template.name, layouts.len())
+    } else {
+        format!("Synced {} replicas of '{}' across {} layouts.", instance_count(&layouts), payload.template.name, layouts.len())
+    };
+    Ok(WithWarnings { value: InstanceEditResult { dry_run: payload.dry_run, message, layouts }, warnings: edited.warnings })
+}
+
+#[tauri::command]
+pub fn detach_replicas(app: tauri::AppHandle, payload: TemplatePayload) -> Result<WithWarnings<InstanceEditResult>, AppError> {
+    let project_base_path = PathBuf::from(&payload.project_path);
+    let tree = template_tree_or_root(&project_base_path, &payload.template)?;
+    let members = project_template_members(&project_base_path, &payload.template, &tree)?;
+    let edited = edit_project_instances_in(&app, &project_base_path, "detach_replicas", payload.dry_run, |context, instance| {
+        members.detach(context, instance)
+    })?;
+    let layouts = edited.value;
+    let message = if payload.dry_run {
+        format!("Dry run: {} replicas of '{}' across {} layouts would be detached.", instance_count(&layouts), payload.template.name, layouts.len())
+    } else {
+        format!("Detached {} replicas of '{}' across {} layouts.", instance_count(&layouts), payload.template.name, layouts.len())
+    };
+    Ok(WithWarnings { value: InstanceEditResult { dry_run: payload.dry_run, message, layouts }, warnings: edited.warnings })
+}
+
+/// Demotes the template instance to a plain instance and detaches all of its replicas.
+#[tauri::command]
+pub fn retire_template(app: tauri::AppHandle, payload: TemplatePayload) -> Result<WithWarnings<InstanceEditResult>, AppError> {
+    let project_base_path = PathBuf::from(&payload.project_path);
+    // Fails early when the template is already gone, rather than reporting nothing.
+    let tree = template_tree(&project_base_path, &payload.template)?;
+    let members = project_template_members(&project_base_path, &payload.template, &tree)?;
+    let mut demoted = false;
+    let edited = edit_project_instances_in(&app, &project_base_path, "retire_template", payload.dry_run, |context, instance| {
+        let is_template = members.is_template(context, instance);
+        if !members.retire(context, instance) {
+            return false;
+        }
+        demoted |= is_template;
+        true
+    })?;
+    let layouts = edited.value;
+    let replicas = instance_count(&layouts) - usize::from(demoted);
+    let message = if payload.dry_run {
+        format!("Dry run: template '{}' would be retired, detaching {} replicas across {} layouts.", payload.template.name, replicas, layouts.len())
+    } else {
+        format!("Retired template '{}' and detached {} replicas across {} layouts.", payload.template.name, replicas, layouts.len())
+    };
+    Ok(WithWarnings { value: InstanceEditResult { dry_run: payload.dry_run, message, layouts }, warnings: edited.warnings })
+}
+
+/// Renames the template instance and points every replica at the new name, all in one write.
+#[tauri::command]
+pub fn rename_template(app: tauri::AppHandle, payload: RenameTemplatePayload) -> Result<WithWarnings<InstanceEditResult>, AppError> {
+    let project_base_path = PathBuf::from(&payload.project_path);
+    let new_name = payload.new_name.trim();
+    if new_name.is_empty() {
+        return Err(AppError::new(AppErrorKind::InvalidInput, "The new template name is empty"));
+    }
+
+    let project = Project::load(&project_base_path)?;
+    let templates = find_project_templates(&project_base_path, &project.layout_entries)?;
+    // A layout we can't read could hold replicas we would miss, so nothing gets renamed.
+    if let Some(unreadable) = templates.warnings.into_iter().next() {
+        return Err(AppError { message: format!("Nothing was renamed, a layout could not be read: {}", unreadable.message), ..unreadable });
+    }
+    if !templates.value.contains(&payload.template) {
+        return Err(AppError::new(AppErrorKind::NotFound, format!("Template '{}' of '{}' was not found", payload.template.name, payload.template.object_type)));
+    }
+    if new_name == payload.template.name {
+        let message = format!("Template '{}' is already named '{}', nothing to rename.", payload.template.name, new_name);
+        return Ok(WithWarnings { value: InstanceEditResult { dry_run: payload.dry_run, message, layouts: Vec::new() }, warnings: Vec::new() });
+    }
+    if let Some(existing) = existing_template(&templates.value, &payload.template.object_type, new_name) {
+        return Err(AppError::new(AppErrorKind::InvalidInput, format!("'{}' already has a template named '{}' in {}", existing.object_type, new_name, existing.defined_in_layout_name)));
+    }
+
+    let tree = template_tree(&project_base_path, &payload.template)?;
+    let members = project_template_members(&project_base_path, &payload.template, &tree)?;
+    let layouts = edit_every_project_instance_in(&app, &project_base_path, "rename_template", payload.dry_run, |context, instance| {
+        members.rename(context, instance, new_name)
+    })?;
+    let message = if payload.dry_run {
+        format!("Dry run: template '{}' would be renamed to '{}', updating {} instances across {} layouts.", payload.template.name, new_name, instance_count(&layouts), layouts.len())
+    } else {
+        format!("Renamed template '{}' to '{}', updating {} instances across {} layouts.", payload.template.name, new_name, instance_count(&layouts), layouts.len())
+    };
+    Ok(WithWarnings { value: InstanceEditResult { dry_run: payload.dry_run, message, layouts }, warnings: Vec::new() })
+}
+
+/// Template names only have to be unique per object type, Construct allows "main" on every type.
+fn existing_template<'a>(templates: &'a [TemplateDefinition], object_type: &str, name: &str) -> Option<&'a TemplateDefinition> {
+    templates.iter().find(|t| t.name == name && t.object_type == object_type)
+}
+
+/// Makes instance `uid` the template `template_name`, returns its object type.
+fn promote_instance(layout_data: &mut Value, uid: u32, template_name: &str, templates: &[TemplateDefinition]) -> Result<String, AppError> {
+    let (_, instance) = layout_instances_mut(layout_data).into_iter()
+        .find(|(_, instance)| instance_uid(instance) == uid)
+        .ok_or_else(|| AppError::new(AppErrorKind::NotFound, format!("No instance with UID {}", uid)))?;
+    let object_type = instance.get("type").and_then(|t| t.as_str()).unwrap_or_default().to_string();
+    match template_field(instance, "mode") {
+        Some("template") => {
+            return Err(AppError::new(AppErrorKind::InvalidInput, format!("Instance {} is already the template '{}'", uid, template_field(instance, "templateName").unwrap_or_default())));
+        }
+        Some("replica") => {
+            return Err(AppError::new(AppErrorKind::InvalidInput, format!("Instance {} is a replica of '{}', detach it first", uid, template_field(instance, "sourceTemplateName").unwrap_or_default())));
+        }
+        _ => {}
+    }
+    if let Some(existing) = existing_template(templates, &object_type, template_name) {
+        return Err(AppError::new(AppErrorKind::InvalidInput, format!("'{}' already has a template named '{}' in {}", object_type, template_name, existing.defined_in_layout_name)));
+    }
+
+    let template_obj = instance.entry("template".to_string())
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| AppError::new(AppErrorKind::InvalidStructure, format!("Instance {} has a 'template' value that is not an object", uid)))?;
+    template_obj.insert("mode".to_string(), Value::String("template".to_string()));
+    template_obj.insert("templateName".to_string(), Value::String(template_name.to_string()));
+    template_obj.insert("sourceTemplateName".to_string(), Value::String("".to_string()));
+    insert_template_defaults(template_obj);
+    Ok(object_type)
+}
+
+#[tauri::command]
+pub fn promote_instance_to_template(app: tauri::AppHandle, payload: PromoteInstancePayload) -> Result<WithWarnings<TemplateDefinition>, AppError> {
+    let project_base_path = PathBuf::from(&payload.project_path);
+    let template_name = payload.template_name.trim();
+    if template_name.is_empty() {
+        return Err(AppError::new(AppErrorKind::InvalidInput, "The template name is empty"));
+    }
+    let project = Project::load(&project_base_path)?;
+    let full_layout_path = project_base_path.join(&payload.layout_file_path);
+    let (mut layout_data, _) = read_json_file(&full_layout_path)?;
+    let templates = find_project_templates(&project_base_path, &project.layout_entries)?;
+    let object_type = promote_instance(&mut layout_data, payload.instance_uid, template_name, &templates.value)
+        .map_err(|e| e.with_file(&full_layout_path))?;
+
+    let mut changes = ChangeSet::new(&project_base_path, "promote_instance_to_template");
+    changes.write_json(full_layout_path, &layout_data)?;
+    changes.commit(&snapshot::app_data_dir(&app)?)?;
+
+    let defined_in_layout_name = project.layout_entries.iter()
+        .find(|l| l.path == payload.layout_file_path)
+        .map(|l| l.name.clone())
+        .unwrap_or_else(|| Path::new(&payload.layout_file_path).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default());
+    let definition = TemplateDefinition {
+        name: template_name.to_string(),
+        object_type,
+        defined_in_layout_name,
+        defined_in_layout_path: payload.layout_file_path,
+    };
+    Ok(WithWarnings { value: definition, warnings: templates.warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn definition(name: &str, object_type: &str) -> TemplateDefinition {
+        TemplateDefinition {
+            name: name.to_string(),
+            object_type: object_type.to_string(),
+            defined_in_layout_name: "Templates".to_string(),
+            defined_in_layout_path: "layouts/Templates.json".to_string(),
+        }
+    }
+
+    fn instance(object_type: &str, uid: u32, template: Value, children: &[u32]) -> Value {
+        json!({ "type": object_type, "uid": uid, "template": template, "children": children })
+    }
+
+    fn layout(instances: Vec<Value>) -> Value {
+        json!({ "layers": [{ "name": "Layer 0", "instances": instances }] })
+    }
+
+    fn edit_layouts(layouts: &mut [(&str, Value)], mut edit: impl FnMut(&InstanceContext, &mut Map<String, Value>) -> bool) {
+        for (path, layout_data) in layouts.iter_mut() {
+            let context = InstanceContext { layout_name: path, layout_path: path, layer_name: "Layer 0" };
+            for (_, instance) in layout_instances_mut(layout_data) {
+                edit(&context, instance);
+            }
+        }
+    }
+
+    fn members(template: &TemplateDefinition, layouts: &[(&str, Value)]) -> TemplateMembers {
+        let tree = layout_template_tree(&layouts[0].1, template).unwrap();
+        TemplateMembers::build(template, &tree, layouts.iter().map(|(path, data)| (*path, data)))
+    }
+
+    fn template_names(layouts: &[(&str, Value)]) -> Vec<(u32, String)> {
+        let mut names = Vec::new();
+        for (_, layout_data) in layouts {
+            for (_, instance) in layout_instances(layout_data) {
+                for key in ["templateName", "sourceTemplateName"] {
+                    if let Some(name) = template_field(instance, key).filter(|n| !n.is_empty()) {
+                        names.push((instance_uid(instance), name.to_string()));
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    #[test]
+    fn rename_and_retire_follow_child_replicas_of_another_type() {
+        let template = |name: &str| json!({ "mode": "template", "templateName": name, "sourceTemplateName": "" });
+        let replica = |name: &str| json!({ "mode": "replica", "templateName": "", "sourceTemplateName": name });
+        let mut layouts = vec![
+            ("layouts/Templates.json", layout(vec![
+                instance("Player", 1, template("main"), &[2]),
+                json!({ "type": "Gun", "uid": 2 }),
+                // Same name, other type: neither it nor its replicas belong to Player's template.
+                instance("Gun", 3, template("main"), &[]),
+            ])),
+            ("layouts/Level.json", layout(vec![
+                instance("Player", 10, replica("main"), &[11]),
+                instance("Gun", 11, replica("main"), &[]),
+                instance("Gun", 20, replica("main"), &[]),
+            ])),
+        ];
+
+        let player_main = definition("main", "Player");
+        let renamed = members(&player_main, &layouts);
+        edit_layouts(&mut layouts, |context, instance| renamed.rename(context, instance, "hero"));
+        assert_eq!(template_names(&layouts), vec![
+            (1, "hero".to_string()),
+            (3, "main".to_string()),
+            (10, "hero".to_string()),
+            (11, "hero".to_string()),
+            (20, "main".to_string()),
+        ]);
+
+        let player_hero = definition("hero", "Player");
+        let retired = members(&player_hero, &layouts);
+        edit_layouts(&mut layouts, |context, instance| retired.retire(context, instance));
+        assert_eq!(template_names(&layouts), vec![(3, "main".to_string()), (20, "main".to_string())]);
+    }
+
+    #[test]
+    fn sync_copies_from_the_matching_template_child() {
+        let mut layouts = vec![
+            ("layouts/Templates.json", layout(vec![
+                instance("Player", 1, json!({ "mode": "template", "templateName": "main" }), &[2]),
+                json!({ "type": "Gun", "uid": 2, "instanceVariables": { "ammo": 30 } }),
+            ])),
+            ("layouts/Level.json", layout(vec![
+                instance("Player", 10, json!({ "mode": "replica", "sourceTemplateName": "main" }), &[11]),
+                json!({ "type": "Gun", "uid": 11, "template": { "mode": "replica", "sourceTemplateName": "main" }, "instanceVariables": { "ammo": 5 } }),
+            ])),
+        ];
+        let synced = members(&definition("main", "Player"), &layouts);
+        edit_layouts(&mut layouts, |context, instance| synced.sync(context, instance, &[SyncProperty::InstanceVariables]));
+        assert_eq!(layouts[1].1["layers"][0]["instances"][1]["instanceVariables"], json!({ "ammo": 30 }));
+        assert!(layouts[1].1["layers"][0]["instances"][0].get("instanceVariables").is_none());
+    }
+    #[test]
+    fn existing_template_only_matches_the_same_object_type() {
+        let templates = vec![definition("main", "Player"), definition("boss", "Enemy")];
+        assert_eq!(existing_template(&templates, "Player", "main"), Some(&templates[0]));
+        assert_eq!(existing_template(&templates, "Enemy", "main"), None);
+        assert_eq!(existing_template(&templates, "Player", "boss"), None);
+    }
+
+    #[test]
+    fn promote_instance_makes_a_plain_instance_a_template() {
+        let mut layout_data = layout(vec![json!({ "type": "Player", "uid": 1 })]);
+        assert_eq!(promote_instance(&mut layout_data, 1, "main", &[]).unwrap(), "Player");
+        let template = &layout_data["layers"][0]["instances"][0]["template"];
+        assert_eq!(template["mode"], "template");
+        assert_eq!(template["templateName"], "main");
+        assert_eq!(template["sourceTemplateName"], "");
+    }
+
+    #[test]
+    fn promote_instance_refuses_templates_replicas_and_taken_names() {
+        let mut layout_data = layout(vec![
+            instance("Player", 1, json!({ "mode": "template", "templateName": "main" }), &[]),
+            instance("Player", 2, json!({ "mode": "replica", "sourceTemplateName": "main" }), &[]),
+            json!({ "type": "Player", "uid": 3 }),
+        ]);
+        let templates = vec![definition("main", "Player")];
+        for (uid, name) in [(1, "other"), (2, "other"), (3, "main")] {
+            let error = promote_instance(&mut layout_data, uid, name, &templates).unwrap_err();
+            assert_eq!(error.kind, AppErrorKind::InvalidInput, "instance {}", uid);
+        }
+        assert_eq!(promote_instance(&mut layout_data, 4, "other", &templates).unwrap_err().kind, AppErrorKind::NotFound);
+        assert!(layout_data["layers"][0]["instances"][2].get("template").is_none());
+    }
+
+    #[test]
+    fn promote_instance_allows_a_name_taken_by_another_type() {
+        let mut layout_data = layout(vec![json!({ "type": "Enemy", "uid": 1 })]);
+        assert_eq!(promote_instance(&mut layout_data, 1, "main", &[definition("main", "Player")]).unwrap(), "Enemy");
+        assert_eq!(layout_data["layers"][0]["instances"][0]["template"]["templateName"], "main");
+    }
+}
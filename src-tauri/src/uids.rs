@@ -0,0 +1,368 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::changes::ChangeSet;
+use crate::error::{AppError, AppErrorKind, WithWarnings};
+use crate::project::Project;
+use crate::templates::{instance_uid, layout_instances_mut, layout_layers};
+use crate::{read_json_file, snapshot};
+
+/// ACE parameters Construct uses for hard-coded UIDs, such as the "Pick by unique ID" condition.
+const UID_PARAMETERS: [&str; 2] = ["unique-id", "uid"];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UidLocation {
+    layout_name: String,
+    layout_path: String,
+    layer_name: String,
+    object_type: String,
+    /// JSON pointer of the instance in its layout.
+    pointer: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DuplicateUid {
+    uid: u32,
+    /// In project order, the first one keeps the UID on repair.
+    locations: Vec<UidLocation>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UidReport {
+    instance_count: usize,
+    max_uid: u32,
+    duplicates: Vec<DuplicateUid>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RepairUidsPayload {
+    project_path: String,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UidReassignment {
+    old_uid: u32,
+    new_uid: u32,
+    location: UidLocation,
+    /// Hierarchy `children` entries of the same layout now pointing at the new UID.
+    children_updated: usize,
+    /// Event sheet parameters now pointing at the new UID.
+    event_references_updated: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UidRepairResult {
+    dry_run: bool,
+    message: String,
+    reassigned: Vec<UidReassignment>,
+}
+
+/// Where every instance UID of the project is used.
+pub struct UidRegistry {
+    by_uid: BTreeMap<u32, Vec<UidLocation>>,
+    /// Event sheet name of every layout path that has one.
+    event_sheets: HashMap<String, String>,
+    /// Instances without a `uid`, left out of `by_uid` since they would all look like duplicates of 0.
+    without_uid: Vec<AppError>,
+}
+
+impl UidRegistry {
+    /// Unreadable layouts and instances without a UID are skipped with a warning.
+    pub fn scan(project_base_path: &Path, project: &Project) -> WithWarnings<UidRegistry> {
+        let mut scanned = UidRegistry::scan_layouts(project_base_path, project);
+        scanned.warnings.extend(scanned.value.without_uid.iter().cloned());
+        scanned
+    }
+
+    /// Like `scan`, but fails on the first unreadable layout since a UID in use could be missed.
+    /// Instances without a UID are still only reported, see `without_uid`.
+    pub fn scan_complete(project_base_path: &Path, project: &Project) -> Result<UidRegistry, AppError> {
+        let scanned = UidRegistry::scan_layouts(project_base_path, project);
+        match scanned.warnings.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(scanned.value),
+        }
+    }
+
+    /// The registry, with the unreadable layouts as warnings.
+    fn scan_layouts(project_base_path: &Path, project: &Project) -> WithWarnings<UidRegistry> {
+        let mut by_uid: BTreeMap<u32, Vec<UidLocation>> = BTreeMap::new();
+        let mut event_sheets = HashMap::new();
+        let mut without_uid = Vec::new();
+        let mut warnings = Vec::new();
+        for entry in &project.layout_entries {
+            let layout_data = match read_json_file(&project_base_path.join(&entry.path)) {
+                Ok((data, _)) => data,
+                Err(e) => {
+                    warnings.push(e);
+                    continue;
+                }
+            };
+            if let Some(sheet) = layout_data.get("eventSheet").and_then(|e| e.as_str()).filter(|e| !e.is_empty()) {
+                event_sheets.insert(entry.path.clone(), sheet.to_string());
+            }
+            for (layer_pointer, layer) in layout_layers(&layout_data) {
+                let layer_name = layer.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+                let instances = layer.get("instances").and_then(|i| i.as_array()).into_iter().flatten();
+                for (index, instance) in instances.enumerate() {
+                    let Some(instance) = instance.as_object() else { continue };
+                    let pointer = format!("{}/instances/{}", layer_pointer, index);
+                    let object_type = instance.get("type").and_then(|t| t.as_str()).unwrap_or_default().to_string();
+                    if instance.get("uid").and_then(|u| u.as_u64()).is_none() {
+                        without_uid.push(AppError::new(AppErrorKind::InvalidStructure, format!("'{}' instance on layer '{}' has no UID", object_type, layer_name))
+                            .with_file(project_base_path.join(&entry.path))
+                            .at_pointer(pointer));
+                        continue;
+                    }
+                    by_uid.entry(instance_uid(instance)).or_default().push(UidLocation {
+                        layout_name: entry.name.clone(),
+                        layout_path: entry.path.clone(),
+                        layer_name: layer_name.to_string(),
+                        object_type,
+                        pointer,
+                    });
+                }
+            }
+        }
+        WithWarnings { value: UidRegistry { by_uid, event_sheets, without_uid }, warnings }
+    }
+
+    pub fn without_uid(&self) -> &[AppError] {
+        &self.without_uid
+    }
+
+    pub fn max_uid(&self) -> u32 {
+        self.by_uid.keys().next_back().copied().unwrap_or(0)
+    }
+
+    pub fn duplicates(&self) -> Vec<DuplicateUid> {
+        self.by_uid.iter()
+            .filter(|(_, locations)| locations.len() > 1)
+            .map(|(uid, locations)| DuplicateUid { uid: *uid, locations: locations.clone() })
+            .collect()
+    }
+
+    fn report(&self) -> UidReport {
+        UidReport {
+            instance_count: self.by_uid.values().map(|l| l.len()).sum(),
+            max_uid: self.max_uid(),
+            duplicates: self.duplicates(),
+        }
+    }
+}
+
+/// Rewrites `children` entries (a UID or an object with a `uid`) equal to `old_uid`, returns how many.
+fn remap_children(layout_data: &mut Value, old_uid: u32, new_uid: u32) -> usize {
+    let mut count = 0;
+    for (_, instance) in layout_instances_mut(layout_data) {
+        let Some(children) = instance.get_mut("children").and_then(|c| c.as_array_mut()) else { continue };
+        for child in children {
+            match child {
+                Value::Object(child_obj) if child_obj.get("uid").and_then(|u| u.as_u64()) == Some(old_uid as u64) => {
+                    child_obj.insert("uid".to_string(), Value::from(new_uid));
+                    count += 1;
+                }
+                Value::Number(n) if n.as_u64() == Some(old_uid as u64) => {
+                    *child = Value::from(new_uid);
+                    count += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+    count
+}
+
+/// Rewrites UID parameters equal to `old_uid` in conditions and actions picking `object_type`, returns how many.
+/// System ones such as "Pick by unique ID" across every type are left alone, the UID could belong to any type.
+fn remap_event_uids(value: &mut Value, object_type: &str, old_uid: u32, new_uid: u32) -> usize {
+    let mut count = 0;
+    match value {
+        Value::Object(map) => {
+            if map.get("objectClass").and_then(|o| o.as_str()) == Some(object_type) {
+                if let Some(parameters) = map.get_mut("parameters").and_then(|p| p.as_object_mut()) {
+                    for key in UID_PARAMETERS {
+                        if let Some(parameter) = parameters.get_mut(key) {
+                            // Parameters are expressions, only a plain number is a hard-coded UID.
+                            let matches = match parameter {
+                                Value::String(expression) => expression.trim().parse::<u64>().ok() == Some(old_uid as u64),
+                                Value::Number(n) => n.as_u64() == Some(old_uid as u64),
+                                _ => false,
+                            };
+                            if matches {
+                                *parameter = match parameter {
+                                    Value::String(_) => Value::String(new_uid.to_string()),
+                                    _ => Value::from(new_uid),
+                                };
+                                count += 1;
+                            }
+                        }
+                    }
+                }
+            }
+            for child in map.values_mut() {
+                count += remap_event_uids(child, object_type, old_uid, new_uid);
+            }
+        }
+        Value::Array(values) => {
+            for child in values {
+                count += remap_event_uids(child, object_type, old_uid, new_uid);
+            }
+        }
+        _ => {}
+    }
+    count
+}
+
+#[tauri::command]
+pub fn find_duplicate_uids(project_path: String) -> Result<WithWarnings<UidReport>, AppError> {
+    let project_base_path = PathBuf::from(&project_path);
+    let project = Project::load(&project_base_path)?;
+    let registry = UidRegistry::scan(&project_base_path, &project);
+    Ok(WithWarnings { value: registry.value.report(), warnings: registry.warnings })
+}
+
+/// Gives every duplicate but the first a UID past the highest one in use. References are only rewritten
+/// in the duplicate's own layout and its event sheet, and only when the instance that kept the UID lives
+/// in another layout, otherwise there's no telling which of the two they meant. For the same reason an event
+/// sheet shared with another layout holding that UID is left alone, with a warning.
+#[tauri::command]
+pub fn repair_duplicate_uids(app: tauri::AppHandle, payload: RepairUidsPayload) -> Result<WithWarnings<UidRepairResult>, AppError> {
+    let project_base_path = PathBuf::from(&payload.project_path);
+    let project = Project::load(&project_base_path)?;
+    let registry = UidRegistry::scan_complete(&project_base_path, &project)?;
+    let mut warnings = registry.without_uid().to_vec();
+    let mut next_uid = registry.max_uid();
+    let mut layouts: HashMap<String, Value> = HashMap::new();
+    let mut event_sheets: HashMap<String, Value> = HashMap::new();
+    let mut changed_event_sheets = HashSet::new();
+    let mut reassigned = Vec::new();
+
+    for duplicate in registry.duplicates() {
+        let kept_layout = &duplicate.locations[0].layout_path;
+        for location in duplicate.locations.iter().skip(1) {
+            next_uid += 1;
+            let layout_path = project_base_path.join(&location.layout_path);
+            if !layouts.contains_key(&location.layout_path) {
+                layouts.insert(location.layout_path.clone(), read_json_file(&layout_path)?.0);
+            }
+            let layout_data = layouts.get_mut(&location.layout_path).ok_or_else(|| AppError::internal("Layout not loaded"))?;
+            let instance = layout_data.pointer_mut(&location.pointer).and_then(|i| i.as_object_mut())
+                .ok_or_else(|| AppError::new(AppErrorKind::Conflict, "Instance moved since the scan").with_file(&layout_path).at_pointer(&location.pointer))?;
+            instance.insert("uid".to_string(), Value::from(next_uid));
+
+            let mut children_updated = 0;
+            let mut event_references_updated = 0;
+            if &location.layout_path != kept_layout {
+                children_updated = remap_children(layout_data, duplicate.uid, next_uid);
+                let event_sheet_name = registry.event_sheets.get(&location.layout_path);
+                let shared_with = duplicate.locations.iter()
+                    .filter(|other| other.layout_path != location.layout_path)
+                    .find(|other| event_sheet_name.is_some() && registry.event_sheets.get(&other.layout_path) == event_sheet_name);
+                let sheet = project.event_sheet_entries.iter().find(|e| Some(&e.name) == event_sheet_name);
+                if let (Some(sheet), Some(other)) = (sheet, shared_with) {
+                    warnings.push(AppError::new(
+                        AppErrorKind::Conflict,
+                        format!(
+                            "'{}' is also the event sheet of '{}', which has an instance with UID {}: its references were left alone",
+                            sheet.name, other.layout_name, duplicate.uid,
+                        ),
+                    ).with_file(project_base_path.join(&sheet.path)));
+                } else if let Some(sheet) = sheet {
+                    if !event_sheets.contains_key(&sheet.path) {
+                        match read_json_file(&project_base_path.join(&sheet.path)) {
+                            Ok((data, _)) => {
+                                event_sheets.insert(sheet.path.clone(), data);
+                            }
+                            Err(e) => warnings.push(e),
+                        }
+                    }
+                    if let Some(sheet_data) = event_sheets.get_mut(&sheet.path) {
+                        event_references_updated = remap_event_uids(sheet_data, &location.object_type, duplicate.uid, next_uid);
+                        if event_references_updated > 0 {
+                            changed_event_sheets.insert(sheet.path.clone());
+                        }
+                    }
+                }
+            }
+            reassigned.push(UidReassignment {
+                old_uid: duplicate.uid,
+                new_uid: next_uid,
+                location: location.clone(),
+                children_updated,
+                event_references_updated,
+            });
+        }
+    }
+
+    let message = if reassigned.is_empty() {
+        "No duplicate UIDs found.".to_string()
+    } else if payload.dry_run {
+        format!("Dry run: {} instances across {} layouts would get a new UID.", reassigned.len(), layouts.len())
+    } else {
+        let mut changes = ChangeSet::new(&project_base_path, "repair_duplicate_uids");
+        let changed_sheets = event_sheets.iter().filter(|(path, _)| changed_event_sheets.contains(*path));
+        for (path, data) in layouts.iter().chain(changed_sheets) {
+            changes.write_json(project_base_path.join(path), data)?;
+        }
+        changes.commit(&snapshot::app_data_dir(&app)?)?;
+        format!("Gave {} instances across {} layouts a new UID.", reassigned.len(), layouts.len())
+    };
+    Ok(WithWarnings { value: UidRepairResult { dry_run: payload.dry_run, message, reassigned }, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn scan_skips_instances_without_a_uid() {
+        let dir = std::env::temp_dir().join(format!("construct-crawler-test-uids-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("layouts")).unwrap();
+        let layout = json!({ "layers": [{ "name": "Layer 0", "instances": [
+            { "type": "Sprite" },
+            { "type": "Sprite", "uid": 0 },
+            { "type": "Text" },
+            { "type": "Text", "uid": 4 }
+        ] }] });
+        std::fs::write(dir.join("layouts/Main.json"), layout.to_string()).unwrap();
+        let project = Project::from_json_str(&json!({ "layouts": { "items": ["Main"] } }).to_string()).unwrap();
+
+        let scanned = UidRegistry::scan(&dir, &project);
+        assert!(scanned.value.duplicates().is_empty());
+        assert_eq!(scanned.value.report().instance_count, 2);
+        let pointers: Vec<_> = scanned.warnings.iter().map(|w| w.json_pointer.clone().unwrap()).collect();
+        assert_eq!(pointers, ["/layers/0/instances/0", "/layers/0/instances/2"]);
+        assert_eq!(UidRegistry::scan_complete(&dir, &project).unwrap().without_uid().len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn remap_event_uids_only_touches_conditions_picking_the_type() {
+        let mut sheet = json!({ "events": [{
+            "conditions": [
+                { "id": "pick-by-unique-id", "objectClass": "Enemy", "parameters": { "unique-id": "7" } },
+                { "id": "pick-by-unique-id", "objectClass": "Player", "parameters": { "unique-id": "7" } },
+                { "id": "pick-by-unique-id", "objectClass": "Enemy", "parameters": { "unique-id": "7 + 1" } }
+            ],
+            "actions": [
+                { "id": "set-layer", "objectClass": "System", "parameters": { "uid": 7 } },
+                { "id": "wait", "parameters": { "uid": 7 } }
+            ],
+            "children": [{ "conditions": [{ "objectClass": "Enemy", "parameters": { "uid": 7 } }] }]
+        }]});
+        assert_eq!(remap_event_uids(&mut sheet, "Enemy", 7, 12), 2);
+        let event = &sheet["events"][0];
+        assert_eq!(event["conditions"][0]["parameters"]["unique-id"], "12");
+        assert_eq!(event["conditions"][1]["parameters"]["unique-id"], "7");
+        assert_eq!(event["conditions"][2]["parameters"]["unique-id"], "7 + 1");
+        assert_eq!(event["actions"][0]["parameters"]["uid"], 7);
+        assert_eq!(event["actions"][1]["parameters"]["uid"], 7);
+        assert_eq!(event["children"][0]["conditions"][0]["parameters"]["uid"], 12);
+    }
+}
@@ -1,20 +1,16 @@
-import fs from "../tauriFsProvider.js";
+import { writeProjectJson } from "../projectJson.js";
 
 export function generateSid() {
   return Math.floor(Math.random() * 1000000000000000);
 }
 
-async function saveToFile(json, src) {
-  await fs.writeFile(src, JSON.stringify(json, null, 2));
-}
-
 export async function moveBehaviorFromMembersToFamily(
   family,
   members,
   behaviorName
 ) {
   let behavior;
-  let promises = [];
+  const writes = [];
 
   const familyJSON = JSON.parse(family.originalJson);
   const familyProps = family.properties;
@@ -42,7 +38,7 @@ export async function moveBehaviorFromMembersToFamily(
       (b) => b.name !== behaviorName
     );
 
-    promises.push(saveToFile(memberJSON, member.src));
+    writes.push({ path: member.src, data: memberJSON });
     member.originalJson = JSON.stringify(memberJSON);
   }
 
@@ -59,8 +55,8 @@ export async function moveBehaviorFromMembersToFamily(
     sid,
   });
 
-  promises.push(saveToFile(familyJSON, family.src));
-  await Promise.all(promises);
+  writes.push({ path: family.src, data: familyJSON });
+  await writeProjectJson("move_behavior_to_family", writes);
 
   family.originalJson = JSON.stringify(familyJSON);
 }
@@ -74,7 +70,7 @@ export async function moveBehaviorFromFamilyToMembers(
 
   const familyJSON = JSON.parse(family.originalJson);
   const familyProps = family.properties;
-  let promises = [];
+  const writes = [];
 
   for (let i = 0; i < familyJSON.behaviorTypes.length; i++) {
     const familyBehavior = familyJSON.behaviorTypes[i];
@@ -93,7 +89,7 @@ export async function moveBehaviorFromFamilyToMembers(
     );
     break;
   }
-  promises.push(saveToFile(familyJSON, family.src));
+  writes.push({ path: family.src, data: familyJSON });
 
   family.originalJson = JSON.stringify(familyJSON);
 
@@ -114,10 +110,10 @@ export async function moveBehaviorFromFamilyToMembers(
       sid,
     });
 
-    promises.push(saveToFile(memberJSON, member.src));
+    writes.push({ path: member.src, data: memberJSON });
 
     member.originalJson = JSON.stringify(memberJSON);
   }
 
-  await Promise.all(promises);
+  await writeProjectJson("move_behavior_to_members", writes);
 }
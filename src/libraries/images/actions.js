@@ -0,0 +1,235 @@
+import { properSizeFormat } from "./utils/sizeUtils";
+
+const resizeModes = ["stretch", "cover", "contain", "alignTopLeft", "alignCenter"];
+const smoothingModes = ["nearest", "bilinear", "bicubic", "hermite", "bezier"];
+const polygonShapes = ["convex", "concave"];
+
+// Image actions run in the backend (apply_image_actions). `toAction` turns the dialog values,
+// in param order, into the action it expects.
+const actions = [
+  {
+    name: "Resize",
+    toAction: (size, mode, smoothing) => ({
+      action: "resize",
+      size,
+      mode: resizeModes[mode],
+      smoothing: smoothingModes[smoothing],
+    }),
+    description: "Resize the image to the specified width and height.",
+    params: [
+      {
+        name: "Size",
+        type: "size",
+        description:
+          "The width to resize the image to. Can be a number, or a percentage string.",
+      },
+      {
+        name: "Mode",
+        type: "combo",
+        options: resizeModes,
+        description: "The resize mode.",
+      },
+      {
+        name: "Smoothing",
+        type: "combo",
+        options: smoothingModes,
+        description: "Algorithm to use for smoothing.",
+      },
+    ],
+  },
+  {
+    name: "Fast Blur",
+    toAction: (radius) => ({ action: "fastBlur", radius }),
+    description:
+      "Applies a quick blur to the image, similar to gaussian blur but much faster.",
+    params: [
+      {
+        name: "Radius",
+        type: "number",
+        description: "The radius of the blur.",
+      },
+    ],
+  },
+  {
+    name: "Gaussian Blur",
+    toAction: (radius) => ({ action: "gaussianBlur", radius }),
+    description: "Applies a gaussian blur to the image.",
+    params: [
+      {
+        name: "Radius",
+        type: "number",
+        description: "The radius of the blur, up to 250 pixels.",
+      },
+    ],
+  },
+  {
+    name: "Alpha Threshold",
+    toAction: (threshold, min, max) => ({ action: "alphaThreshold", threshold, min, max }),
+    description: "Applies an alpha threshold to the image.",
+    params: [
+      {
+        name: "Threshold",
+        type: "number",
+        description: "The threshold to apply.",
+        value: 128,
+      },
+      {
+        name: "Min",
+        type: "number",
+        description: "The minimum alpha value.",
+      },
+      {
+        name: "Max",
+        type: "number",
+        description: "The maximum alpha value.",
+        value: 255,
+      },
+    ],
+  },
+  {
+    name: "Replace Image",
+    toAction: (path) => ({ action: "replaceImage", path }),
+    description: "Replaces the image with another image.",
+    params: [
+      {
+        name: "Image",
+        type: "imagePath",
+        options: {
+          label: "Select an image",
+        },
+        description: "The PNG image to replace with.",
+      },
+    ],
+  },
+  {
+    name: "Trim",
+    toAction: (cutoff, padding, shared_box) => ({ action: "trim", cutoff, padding, shared_box }),
+    description:
+      "Crops the image to its visible pixels. Origin, image points and collision polygon are moved to match.",
+    params: [
+      {
+        name: "Alpha Cutoff",
+        type: "number",
+        description: "Pixels with an alpha at or below this value (0-255) count as transparent.",
+      },
+      {
+        name: "Padding",
+        type: "number",
+        description: "Transparent pixels to keep around the visible ones.",
+      },
+      {
+        name: "Shared Box",
+        type: "boolean",
+        description:
+          "Crop every frame of an animation to the same box, so the frames keep lining up.",
+        value: true,
+      },
+    ],
+  },
+  {
+    name: "Trace Collision Polygon",
+    toAction: (threshold, max_vertices, shape) => ({
+      action: "traceCollision",
+      threshold,
+      max_vertices,
+      shape: polygonShapes[shape],
+    }),
+    description:
+      "Sets the collision polygon to the outline of the visible pixels. The image is not changed.",
+    params: [
+      {
+        name: "Alpha Threshold",
+        type: "number",
+        description: "Pixels with an alpha at or below this value (0-255) count as transparent.",
+        value: 128,
+      },
+      {
+        name: "Max Vertices",
+        type: "number",
+        description: "The most points the polygon can have, at least 3.",
+        value: 8,
+      },
+      {
+        name: "Shape",
+        type: "combo",
+        options: polygonShapes,
+        description:
+          "Convex wraps every visible pixel, concave follows the outline of the largest visible area.",
+      },
+    ],
+  },
+  {
+    name: "Set All To Replica",
+    description: "Set layout instances of selected object types as replicas.",
+    params: [
+      {
+        name: "templateName",
+        label: "Template name",
+        type: "text",
+        default: "main",
+        description: "Name of the template to assign to replicas."
+      }
+    ],
+    function: async (selectedObjectTypes, helpers, params) => {
+      const layouts = helpers.store.project.projectData.layouts || [];
+      const templateName = params.templateName;
+  
+      for (const layout of layouts) {
+        for (const layer of layout.properties?.layers || []) {
+          for (const instance of layer.instances || []) {
+            const isMatch = selectedObjectTypes.some(obj => obj.name === instance.type);
+            if (!isMatch) continue;
+  
+            instance.template = {
+              mode: "replica",
+              sourceTemplateName: templateName,
+              templateName: "",
+              replicaHierarchyInSyncWithTemplate: false,
+              templatePropagateHierarchyChanges: true,
+              replicaIgnoreTemplateHierarchyChanges: false,
+              components: []
+            };
+  
+            helpers.log(`[INFO] Set ${instance.type} in layout "${layout.name}" to replica of "${templateName}"`);
+          }
+        }
+      }
+  
+      helpers.store.markAsDirty();
+    }
+  }
+];
+
+export default actions;
+
+export const imageActions = actions.filter((action) => action.toAction);
+
+// Dialog state for the params of an action. Sizes start at `size`, or at 100% without one.
+export function createParams(action, size) {
+  return action.params.map((param) => {
+    const ret = { ...param };
+    if (param.type === "size") {
+      ret.value = {
+        width: size ? size.width : "100%",
+        autoWidth: false,
+        height: size ? size.height : "100%",
+        autoHeight: false,
+      };
+    } else if (param.type === "combo") {
+      ret.value = 0;
+      ret.options = param.options.map((option, i) => ({ label: option, value: i }));
+    } else if (param.type === "number") {
+      ret.value = param.value || 0;
+    } else if (param.type === "boolean") {
+      ret.value = !!param.value;
+    }
+    return ret;
+  });
+}
+
+// The action sent to the backend for params made by createParams.
+export function buildAction(action, params) {
+  return action.toAction(
+    ...params.map((param) => (param.type === "size" ? properSizeFormat(param.value) : param.value))
+  );
+}
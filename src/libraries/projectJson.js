@@ -0,0 +1,14 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { useAppStore } from "../store/appStore";
+
+// Writes project JSON files (object types, families, layouts) through the backend, which keeps
+// Construct's formatting and records the edit for undo. `files` is [{ path: string, data: object }].
+export async function writeProjectJson(command, files) {
+  const store = useAppStore();
+  await invoke("write_project_json", {
+    projectPath: store.project.path,
+    command,
+    files,
+  });
+  await store.fetchJournal();
+}
@@ -10,6 +10,11 @@ import Family from "../views/Family.vue";
 // Import new views
 import Layouts from "../views/Layouts.vue";
 import LayoutDetail from "../views/LayoutDetail.vue";
+import Snapshots from "../views/Snapshots.vue";
+import Templates from "../views/Templates.vue";
+import Uids from "../views/Uids.vue";
+import BulkEdit from "../views/BulkEdit.vue";
+import ImageBatch from "../views/ImageBatch.vue";
 
 const routes = [
   { path: "/", name: "Home", component: Home },
@@ -48,6 +53,11 @@ const routes = [
           }
         ]
       },
+      { path: "templates", name: "Templates", component: Templates },
+      { path: "uids", name: "Uids", component: Uids },
+      { path: "bulk-edit", name: "BulkEdit", component: BulkEdit },
+      { path: "image-batch", name: "ImageBatch", component: ImageBatch },
+      { path: "snapshots", name: "Snapshots", component: Snapshots },
     ],
   },
 ];
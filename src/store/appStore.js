@@ -6,8 +6,16 @@ import { router } from "../plugins/router.js";
 import { appWindow } from "@tauri-apps/api/window";
 import { invoke } from "@tauri-apps/api/tauri";
 
-function zeroPad(num, places) {
-  return String(num).padStart(places, "0");
+// Errors from Rust commands are { kind, file, json_pointer, xml_position, message } objects.
+export function formatError(error) {
+  if (typeof error === "string") return error;
+  if (error && error.kind) {
+    let location = error.file || "";
+    if (error.json_pointer) location += ` at ${error.json_pointer}`;
+    if (error.xml_position !== null && error.xml_position !== undefined) location += ` at byte ${error.xml_position}`;
+    return location ? `${error.message} (${location})` : error.message;
+  }
+  return (error && error.message) || "An unknown error occurred";
 }
 
 export const useAppStore = defineStore("app", {
@@ -19,8 +27,9 @@ export const useAppStore = defineStore("app", {
     project: {
       path: "",
       name: "",
+      sourceC3pPath: "", // Original .c3p archive when the project was opened from a single file
       projectData: {
-        c3proj: {}, // Will hold the parsed project.c3proj content
+        c3proj: {}, // Will hold the project model returned by load_project
         objectTypes: [],
         objectTypesByName: new Map(),
         families: [],
@@ -34,7 +43,10 @@ export const useAppStore = defineStore("app", {
     familySearch: "",
     layoutSearch: "",
     currentLayoutInstances: [],
+    currentLayout: null, // Typed layout model (layers, sub_layers, instances) returned by get_layout
     selectedInstancesForReplica: new Set(),
+    snapshots: [],
+    journal: { operations: [], position: 0 }, // Undo/redo history kept by the backend
   }),
   getters: {},
   actions: {
@@ -48,6 +60,7 @@ export const useAppStore = defineStore("app", {
       this.project = {
         path: "",
         name: "",
+        sourceC3pPath: "",
         projectData: {
           c3proj: {},
           objectTypes: [],
@@ -60,6 +73,8 @@ export const useAppStore = defineStore("app", {
       };
       this.currentLayoutInstances = [];
       this.selectedInstancesForReplica.clear();
+      this.snapshots = [];
+      this.journal = { operations: [], position: 0 };
       await router.push({ path: "/" });
     },
     async saveToFile() {
@@ -87,13 +102,43 @@ export const useAppStore = defineStore("app", {
           this.projectLoadingMessage = 'Project saved successfully!';
         } catch (e) {
           this.logError(e);
-          this.projectLoadingMessage = `Error saving: ${e}`;
+          this.projectLoadingMessage = `Error saving: ${formatError(e)}`;
         } finally {
           this.loading = false;
           setTimeout(() => { if(this.projectLoadingMessage.includes('successfully') || this.projectLoadingMessage.includes('Error')) this.projectLoadingMessage = ''; }, 5000);
         }
       }
     },
+    async saveToOriginalC3p() {
+      if (!this.project.sourceC3pPath) {
+        this.logError("This project was not opened from a .c3p file.");
+        return;
+      }
+      this.loading = true;
+      this.projectLoadingProgress = 0;
+      this.projectLoadingMessage = "Writing changes back to C3P...";
+
+      const unlisten = await appWindow.listen("progress", (event) => {
+        this.projectLoadingProgress = event.payload.progress;
+        this.projectLoadingMessage = "Writing changes back to C3P...\n" + event.payload.filename;
+        if (event.payload.done) unlisten();
+      });
+
+      try {
+        await invoke("write_back_c3p", {
+          dirPath: this.project.path,
+          zipPath: this.project.sourceC3pPath,
+        });
+        this.projectLoadingMessage = 'Project saved successfully!';
+      } catch (e) {
+        unlisten();
+        this.logError(e);
+        this.projectLoadingMessage = `Error saving: ${formatError(e)}`;
+      } finally {
+        this.loading = false;
+        setTimeout(() => { if(this.projectLoadingMessage.includes('successfully') || this.projectLoadingMessage.includes('Error')) this.projectLoadingMessage = ''; }, 5000);
+      }
+    },
     async openC3Project() {
       try {
         const dir = await open({ directory: true, multiple: false });
@@ -124,13 +169,22 @@ export const useAppStore = defineStore("app", {
           }
           await fs.createDir(folderPath);
           this.projectLoadingMessage = "Extracting project...";
+          const extractionWarnings = [];
           const unlisten = await appWindow.listen("progress", (event) => {
             this.projectLoadingProgress = event.payload.progress;
             this.projectLoadingMessage = "Extracting project...\n" + event.payload.filename;
-            if (event.payload.done) unlisten();
+            if (event.payload.done) {
+              (event.payload.rejected_entries || []).forEach((entry) => {
+                extractionWarnings.push(`[WARNING]: Skipped archive entry ${entry.name}: ${entry.reason}`);
+              });
+              if (event.payload.history_cleared) {
+                extractionWarnings.push("[WARNING]: Undo history and snapshots of the previously opened project were cleared.");
+              }
+              unlisten();
+            }
           });
           await invoke("extract_zip", { zipPath: filePath, destPath: folderPath });
-          await this.openPath(folderPath);
+          await this.openPath(folderPath, filePath, extractionWarnings);
         } else if (filePath) {
           this.logError("No .c3p file selected.");
         }
@@ -139,6 +193,9 @@ export const useAppStore = defineStore("app", {
         this.loading = false; // Ensure loading is reset on error
       }
     },
+    logWarnings(warnings) {
+      (warnings || []).forEach((warning) => this.logLine(`[WARNING]: ${formatError(warning)}`));
+    },
     logLine(line) {
       this.log += line + "\n";
       console.log(line);
@@ -150,152 +207,49 @@ export const useAppStore = defineStore("app", {
         } catch (e) { console.error("Failed to dump log:", e); }
       }
     },
-    async openPath(projectDir) {
+    async openPath(projectDir, sourceC3pPath = "", extractionWarnings = []) {
       this.log = "";
+      extractionWarnings.forEach((line) => this.logLine(line));
       this.projectLoadingMessage = "Loading project...";
       this.projectLoadingProgress = 0;
       this.loading = true;
 
       try {
-        const fsPath = path;
-        const c3projPath = await fsPath.join(projectDir, "project.c3proj");
-        if (!await fs.exists(c3projPath)) {
-            throw new Error("project.c3proj not found at specified path.");
-        }
-        const c3projContent = await fs.readFile(c3projPath);
-        const c3projDataForJs = JSON.parse(c3projContent);
-
-        this.projectLoadingMessage = "Loading layouts list...";
-        let layouts = [];
-        const c3projIsJsonFormat = typeof c3projDataForJs.layouts === 'object' && c3projDataForJs.layouts !== null;
-
-        if (c3projIsJsonFormat) {
-          this.projectLoadingMessage = "Processing layouts from project.json...";
-          const parsedLayoutsFromJson = [];
-          const processLayoutsFolder = (folder, currentPathParts) => {
-            (folder.items || []).forEach(itemName => {
-              const layoutFilePathParts = ["layouts", ...currentPathParts, `${itemName}.json`];
-              const relativeLayoutPath = layoutFilePathParts.join('/');
-              // Check if this layout already exists by path to avoid duplicates if structure is odd
-              if (!parsedLayoutsFromJson.some(l => l.path === relativeLayoutPath)) {
-                 parsedLayoutsFromJson.push({ name: itemName, path: relativeLayoutPath });
-              }
-            });
-
-            (folder.subfolders || []).forEach(subfolder => {
-              // Ensure subfolder.name is valid before adding to path
-              if (subfolder.name && typeof subfolder.name === 'string' && subfolder.name.trim() !== '') {
-                processLayoutsFolder(subfolder, [...currentPathParts, subfolder.name]);
-              } else {
-                processLayoutsFolder(subfolder, currentPathParts); // Process items in unnamed folder
-              }
-            });
-          };
-
-          if (c3projDataForJs.layouts) {
-            processLayoutsFolder(c3projDataForJs.layouts, []);
-          }
-          this.logLine(`[INFO] Loaded ${parsedLayoutsFromJson.length} layouts directly from JSON project file.`);
-          layouts = parsedLayoutsFromJson;
-        } else {
-          this.projectLoadingMessage = "Loading layouts list (XML fallback)...";
-          this.logLine("[INFO] project.c3proj does not appear to use JSON for layouts structure. Attempting XML parse via Rust.");
-          try {
-            layouts = await invoke("get_layout_list_from_c3proj", { projectC3projPath: c3projPath });
-            this.logLine(`[INFO] Rust XML parser returned ${layouts.length} layouts.`);
-          } catch (e) {
-            this.logError(`Error loading layouts via Rust XML parser: ${e}`);
-            layouts = []; // Ensure layouts is an empty array on error
-          }
+        const unlisten = await appWindow.listen("progress", (event) => {
+          this.projectLoadingProgress = event.payload.progress;
+          this.projectLoadingMessage = "Loading project...\n" + event.payload.filename;
+          if (event.payload.done) unlisten();
+        });
+        let index;
+        try {
+          index = await invoke("index_project", { projectPath: projectDir });
+        } catch (e) {
+          unlisten();
+          throw e;
         }
+        const c3projData = index.project;
+        const layouts = c3projData.layout_entries;
+        this.logLine(`[INFO] Loaded ${layouts.length} layouts from ${c3projData.format.toUpperCase()} project file.`);
+        this.logWarnings(index.warnings);
 
-        const objectTypesByName = new Map();
-        const allObjectTypes = [];
-        const getObjectTypeInfo = async (name, objectTypePath) => {
-          const src = await fsPath.join(projectDir, "objectTypes", objectTypePath, `${name}.json`);
-          const info = { name, src, path: objectTypePath };
-          if (await fs.exists(src)) {
-            const untouchedJson = await fs.readFile(src);
-            const json = JSON.parse(untouchedJson);
-            info.properties = json;
-            info.originalJson = untouchedJson;
-            if (info.properties.animations) {
-              const processAnimFolder = (animations) => {
-                (animations.items || []).forEach((animation) => {
-                  (animation.frames || []).forEach(async (frame, i) => {
-                    const imageName = [name.toLowerCase(), animation.name.toLowerCase(), zeroPad(i, 3)].join("-");
-                    const imagePath = await fsPath.join(projectDir, "images", `${imageName}.png`);
-                    if (await fs.exists(imagePath)) frame.src = imagePath;
-                    else this.logLine(`[WARNING]: Image ${imageName}.png not found for ${name}/${animation.name}`);
-                  });
-                });
-                if (animations.subfolders) animations.subfolders.forEach(processAnimFolder);
-              };
-              processAnimFolder(info.properties.animations);
-            }
-            if (info.properties.image) {
-              const imagePath = await fsPath.join(projectDir, "images", `${name.toLowerCase()}.png`);
-              if (await fs.exists(imagePath)) info.properties.image.src = imagePath;
-              else this.logLine(`[WARNING]: Image ${name.toLowerCase()}.png not found for ${name}`);
-            }
-          } else this.logLine(`[ERROR]: No object type file found for ${name} at ${src}`);
-          return info;
-        };
-        let totalObjectTypes = 0;
-        const countObjectTypes = (folder) => { totalObjectTypes += (folder.items || []).length; (folder.subfolders || []).forEach(countObjectTypes); };
-        countObjectTypes(c3projDataForJs.objectTypes);
-        let currentObjectType = 0;
-        const addObjectTypesToList = async (folder, currentPath) => {
-          for (const item of (folder.items || [])) {
-            currentObjectType++;
-            this.projectLoadingMessage = `Loading object type: ${currentPath ? currentPath + '/' : ''}${item}`;
-            this.projectLoadingProgress = currentObjectType / (totalObjectTypes || 1);
-            const info = await getObjectTypeInfo(item, currentPath);
-            objectTypesByName.set(item, info);
-            allObjectTypes.push(info);
-          }
-          for (const subfolder of (folder.subfolders || [])) {
-            await addObjectTypesToList(subfolder, `${currentPath}${currentPath === "" ? "" : "/"}${subfolder.name}`);
-          }
-        };
-        await addObjectTypesToList(c3projDataForJs.objectTypes, "");
-
-        const familiesByName = new Map();
-        const allFamilies = [];
-        const getFamilyInfo = async (name, familyPath) => {
-            const src = await fsPath.join(projectDir, "families", familyPath, `${name}.json`);
-            const info = { name, src, path: familyPath };
-            if (await fs.exists(src)) {
-                const untouchedJson = await fs.readFile(src);
-                info.properties = JSON.parse(untouchedJson);
-                info.originalJson = untouchedJson;
-            } else this.logLine(`[ERROR]: No family file found for ${name} at ${src}`);
-            return info;
-        };
-        let totalFamilies = 0;
-        const countFamilies = (folder) => { totalFamilies += (folder.items || []).length; (folder.subfolders || []).forEach(countFamilies);};
-        countFamilies(c3projDataForJs.families);
-        let currentFamily = 0;
-        const addFamilyToList = async (folder, currentPath) => {
-            for (const item of (folder.items || [])) {
-                currentFamily++;
-                this.projectLoadingMessage = `Loading family: ${currentPath ? currentPath + '/' : ''}${item}`;
-                this.projectLoadingProgress = currentFamily / (totalFamilies || 1); // Simplified progress for now
-                const info = await getFamilyInfo(item, currentPath);
-                familiesByName.set(item, info);
-                allFamilies.push(info);
-            }
-            for (const subfolder of (folder.subfolders || [])) {
-                await addFamilyToList(subfolder, `${currentPath}${currentPath === "" ? "" : "/"}${subfolder.name}`);
-            }
-        };
-        await addFamilyToList(c3projDataForJs.families, "");
+        const toInfo = (entry) => ({
+          name: entry.name,
+          src: entry.src,
+          path: entry.path,
+          properties: entry.properties,
+          originalJson: entry.original_json,
+        });
+        const allObjectTypes = index.object_types.map(toInfo);
+        const objectTypesByName = new Map(allObjectTypes.map((info) => [info.name, info]));
+        const allFamilies = index.families.map(toInfo);
+        const familiesByName = new Map(allFamilies.map((info) => [info.name, info]));
 
         this.project = {
           path: projectDir,
-          name: c3projDataForJs.name,
+          name: c3projData.name,
+          sourceC3pPath,
           projectData: {
-            c3proj: c3projDataForJs,
+            c3proj: c3projData,
             objectTypes: allObjectTypes,
             objectTypesByName,
             families: allFamilies,
@@ -308,12 +262,13 @@ export const useAppStore = defineStore("app", {
         this.projectLoadingMessage = "Loading project templates...";
         this.projectLoadingProgress = 0;
         await this.fetchProjectTemplates();
+        await this.fetchJournal();
 
         this.logLine(`[INFO]: Project ${this.project.name} opened.`);
         this.projectOpened = true;
         await router.push({ path: "/project/home" });
       } catch (error) {
-        this.logError(`Error opening project path ${projectDir}: ${error}`);
+        this.logError(`Error opening project path ${projectDir}: ${formatError(error)}`);
         await this.clearProject(); // Reset state on critical error
       } finally {
         this.loading = false;
@@ -330,9 +285,10 @@ export const useAppStore = defineStore("app", {
                 projectPathStr: this.project.path,
                 layoutEntries: this.project.projectData.layouts
             });
-            this.project.projectData.projectTemplates = templates;
+            this.logWarnings(templates.warnings);
+            this.project.projectData.projectTemplates = templates.value;
         } catch (error) {
-            this.logError(`Error fetching project templates: ${error}`);
+            this.logError(`Error fetching project templates: ${formatError(error)}`);
             this.project.projectData.projectTemplates = [];
         } finally {
             this.projectLoadingMessage = "";
@@ -349,45 +305,130 @@ export const useAppStore = defineStore("app", {
         });
         this.currentLayoutInstances = instances;
       } catch (error) {
-        this.logError(`Error fetching layout instances for ${layoutFilePath}: ${error}`);
+        this.logError(`Error fetching layout instances for ${layoutFilePath}: ${formatError(error)}`);
       } finally {
       }
     },
-    async applySetReplicas(layoutFilePath, targetTemplateName) {
+    async fetchLayout(layoutFilePath) {
+      if (!this.project || !this.project.path) return;
+      try {
+        this.currentLayout = await invoke('get_layout', {
+          projectPath: this.project.path,
+          layoutFilePath: layoutFilePath,
+        });
+      } catch (error) {
+        this.currentLayout = null;
+        this.logError(`Error fetching layout ${layoutFilePath}: ${formatError(error)}`);
+      }
+    },
+    // Layer commands all take the layout path and return { message, layout }.
+    async editLayers(command, layoutFilePath, payload, description) {
+      if (!this.project || !this.project.path) return null;
+      this.loading = true;
+      this.projectLoadingMessage = `${description}...`;
+      try {
+        const result = await invoke(command, {
+          payload: { project_path: this.project.path, layout_file_path: layoutFilePath, ...payload },
+        });
+        this.logLine(`[INFO] ${result.message}`);
+        this.currentLayout = result.layout;
+        await this.fetchJournal();
+        await this.fetchLayoutInstances(layoutFilePath);
+        return result;
+      } catch (error) {
+        this.logError(`Error: ${description}: ${formatError(error)}`);
+        return null;
+      } finally {
+        this.loading = false;
+        this.projectLoadingMessage = '';
+      }
+    },
+    async renameLayer(layoutFilePath, layerName, newName) {
+      return this.editLayers('rename_layer', layoutFilePath, { layer_name: layerName, new_name: newName }, `Renaming layer '${layerName}'`);
+    },
+    async moveLayer(layoutFilePath, layerName, parentLayerName, index) {
+      return this.editLayers('move_layer', layoutFilePath, { layer_name: layerName, parent_layer_name: parentLayerName, index }, `Moving layer '${layerName}'`);
+    },
+    async duplicateLayer(layoutFilePath, layerName, newName = null) {
+      return this.editLayers('duplicate_layer', layoutFilePath, { layer_name: layerName, new_name: newName }, `Duplicating layer '${layerName}'`);
+    },
+    async deleteLayer(layoutFilePath, layerName) {
+      return this.editLayers('delete_layer', layoutFilePath, { layer_name: layerName }, `Deleting layer '${layerName}'`);
+    },
+    async moveInstancesToLayer(layoutFilePath, instanceUids, targetLayerName) {
+      return this.editLayers('move_instances_to_layer', layoutFilePath, { instance_uids: instanceUids, target_layer_name: targetLayerName }, `Moving instances to layer '${targetLayerName}'`);
+    },
+    async applySetReplicas(layoutFilePath, targetTemplateName, dryRun = false) {
       if (!this.project || !this.project.path || this.selectedInstancesForReplica.size === 0 || !targetTemplateName) {
         this.logError("Missing data for setting replicas. Ensure instances and a target template are selected.");
-        return;
+        return null;
+      }
+      const payload = {
+        project_path: this.project.path,
+        layout_file_path: layoutFilePath,
+        instance_uids: Array.from(this.selectedInstancesForReplica),
+        target_template_name: targetTemplateName,
+        dry_run: dryRun,
+      };
+      if (dryRun) {
+        // Previews don't touch the disk, no need to block the UI or refresh anything.
+        try {
+          const result = await invoke('set_instances_as_replicas', { payload });
+          this.logWarnings(result.warnings);
+          return result.value;
+        } catch (error) {
+          this.logError(`Error previewing replicas: ${formatError(error)}`);
+          return null;
+        }
       }
       this.loading = true;
       this.projectLoadingMessage = `Setting instances in ${layoutFilePath} as replicas of ${targetTemplateName}...`;
       try {
-        await invoke('set_instances_as_replicas', {
-          payload: {
-            project_path: this.project.path,
-            layout_file_path: layoutFilePath,
-            instance_uids: Array.from(this.selectedInstancesForReplica),
-            target_template_name: targetTemplateName,
-          },
-        });
+        const result = await invoke('set_instances_as_replicas', { payload });
+        this.logLine(`[INFO] ${result.value.message}`);
+        for (const rejected of result.value.rejected) {
+          this.logLine(`[WARNING]: UID ${rejected.uid} in ${rejected.layout_path} was not changed: ${rejected.reason}`);
+        }
+        this.logWarnings(result.warnings);
+        await this.fetchJournal();
         await this.fetchLayoutInstances(layoutFilePath); // Refresh instances
         this.projectLoadingMessage = 'Successfully set replicas!';
         this.selectedInstancesForReplica.clear(); // Clear selection after successful operation
+        return result.value;
       } catch (error) {
-        this.logError(`Error setting instances as replicas: ${error}`);
-        this.projectLoadingMessage = `Error: ${error}`;
+        this.logError(`Error setting instances as replicas: ${formatError(error)}`);
+        this.projectLoadingMessage = `Error: ${formatError(error)}`;
+        return null;
       } finally {
         this.loading = false;
         setTimeout(() => { if (this.projectLoadingMessage.includes('Successfully') || this.projectLoadingMessage.includes('Error')) this.projectLoadingMessage = ''; }, 5000);
       }
     },
-    async setAllInstancesOfTypeAsReplicasProjectWide(objectTypeName, targetTemplateName) {
+    async setAllInstancesOfTypeAsReplicasProjectWide(objectTypeName, targetTemplateName, dryRun = false) {
       if (!this.project || !this.project.path || !objectTypeName || !targetTemplateName) {
         this.logError("Missing data for project-wide replica setting. Ensure object type and target template are selected.");
-        return;
+        return null;
       }
       if (!this.project.projectData.layouts || this.project.projectData.layouts.length === 0) {
         this.logError("No layouts found in the project to process.");
-        return;
+        return null;
+      }
+      const payload = {
+        project_path: this.project.path,
+        layout_file_paths: this.project.projectData.layouts.map(l => l.path),
+        object_type_name: objectTypeName,
+        target_template_name: targetTemplateName,
+        dry_run: dryRun,
+      };
+      if (dryRun) {
+        try {
+          const result = await invoke('set_all_instances_of_type_as_replicas_project_wide', { payload });
+          this.logWarnings(result.warnings);
+          return result.value;
+        } catch (error) {
+          this.logError(`Error previewing project-wide replicas: ${formatError(error)}`);
+          return null;
+        }
       }
     
       this.loading = true; 
@@ -395,17 +436,11 @@ export const useAppStore = defineStore("app", {
       this.projectLoadingProgress = 0; 
     
       try {
-        const layoutFilePaths = this.project.projectData.layouts.map(l => l.path);
-        const resultMessage = await invoke('set_all_instances_of_type_as_replicas_project_wide', {
-          payload: {
-            project_path: this.project.path,
-            layout_file_paths: layoutFilePaths,
-            object_type_name: objectTypeName,
-            target_template_name: targetTemplateName,
-          },
-        });
-        this.projectLoadingMessage = resultMessage; 
-        this.logLine(`[INFO] ${resultMessage}`);
+        const result = await invoke('set_all_instances_of_type_as_replicas_project_wide', { payload });
+        this.projectLoadingMessage = result.value.message;
+        this.logLine(`[INFO] ${result.value.message}`);
+        this.logWarnings(result.warnings);
+        await this.fetchJournal();
         
         this.currentLayoutInstances = []; // Invalidate, will refetch if LayoutDetail is active
         this.selectedInstancesForReplica.clear();
@@ -417,11 +452,12 @@ export const useAppStore = defineStore("app", {
         }
         // Also good to refresh project templates, as an object type might newly become a template source effectively
         await this.fetchProjectTemplates();
-    
+        return result.value;
     
       } catch (error) {
-        this.logError(`Error setting project-wide replicas: ${error}`);
-        this.projectLoadingMessage = `Error setting project-wide replicas: ${error.toString().substring(0, 200)}...`;
+        this.logError(`Error setting project-wide replicas: ${formatError(error)}`);
+        this.projectLoadingMessage = `Error setting project-wide replicas: ${formatError(error).substring(0, 200)}...`;
+        return null;
       } finally {
         this.loading = false;
         setTimeout(() => { 
@@ -432,6 +468,191 @@ export const useAppStore = defineStore("app", {
         this.projectLoadingProgress = 0;
       }
     },
+    // Runs a backend command returning an InstanceEditResult. Dry runs only return the preview.
+    async invokeProjectEdit(command, payload, dryRun, description) {
+      if (!this.project || !this.project.path) return null;
+      payload = { project_path: this.project.path, ...payload, dry_run: dryRun };
+      if (!dryRun) {
+        this.loading = true;
+        this.projectLoadingMessage = `${description}...`;
+      }
+      try {
+        const result = await invoke(command, { payload });
+        this.logWarnings(result.warnings);
+        if (!dryRun) {
+          this.logLine(`[INFO] ${result.value.message}`);
+          await this.fetchJournal();
+          await this.fetchProjectTemplates();
+          this.currentLayoutInstances = [];
+        }
+        return result.value;
+      } catch (error) {
+        this.logError(`Error: ${description}: ${formatError(error)}`);
+        return null;
+      } finally {
+        this.loading = false;
+        this.projectLoadingMessage = '';
+      }
+    },
+    async syncReplicasWithTemplate(template, properties, dryRun = false) {
+      return this.invokeProjectEdit('sync_replicas_with_template', { template, properties }, dryRun, `Syncing replicas of '${template.name}'`);
+    },
+    async detachReplicas(template, dryRun = false) {
+      return this.invokeProjectEdit('detach_replicas', { template }, dryRun, `Detaching replicas of '${template.name}'`);
+    },
+    async renameTemplate(template, newName, dryRun = false) {
+      return this.invokeProjectEdit('rename_template', { template, new_name: newName }, dryRun, `Renaming template '${template.name}'`);
+    },
+    async retireTemplate(template, dryRun = false) {
+      return this.invokeProjectEdit('retire_template', { template }, dryRun, `Retiring template '${template.name}'`);
+    },
+    async bulkEditInstances(filter, assignments, dryRun = false) {
+      return this.invokeProjectEdit('bulk_edit_instances', { filter, assignments }, dryRun, 'Editing instances');
+    },
+    async replaceObjectType(fromType, toType, variableMap, behaviorMap, dryRun = false) {
+      return this.invokeProjectEdit('replace_object_type', {
+        from_type: fromType,
+        to_type: toType,
+        variable_map: variableMap,
+        behavior_map: behaviorMap,
+      }, dryRun, `Replacing '${fromType}' with '${toType}'`);
+    },
+    // Runs an image action chain over a target ({ kind: "frame" | "animation" | "objectType" | "family" | "folder", ... }).
+    async applyImageActions(target, actions) {
+      if (!this.project || !this.project.path) return null;
+      this.loading = true;
+      this.projectLoadingProgress = 0;
+      this.projectLoadingMessage = "Processing images...";
+      const unlisten = await appWindow.listen("progress", (event) => {
+        this.projectLoadingProgress = event.payload.progress;
+        this.projectLoadingMessage = "Processing images...\n" + event.payload.filename;
+        if (event.payload.done) unlisten();
+      });
+      try {
+        const result = await invoke("apply_image_actions", {
+          payload: { project_path: this.project.path, target, actions },
+        });
+        result.value.files
+          .filter((file) => file.error)
+          .forEach((file) => this.logLine(`[WARNING]: ${formatError(file.error)}`));
+        this.logWarnings(result.warnings);
+        this.logLine(`[INFO] ${result.value.message}`);
+        await this.fetchJournal();
+        return result.value;
+      } catch (error) {
+        unlisten();
+        this.logError(`Error processing images: ${formatError(error)}`);
+        return null;
+      } finally {
+        this.loading = false;
+        this.projectLoadingMessage = "";
+        this.projectLoadingProgress = 0;
+      }
+    },
+    async findDuplicateUids() {
+      if (!this.project || !this.project.path) return null;
+      try {
+        const result = await invoke('find_duplicate_uids', { projectPath: this.project.path });
+        this.logWarnings(result.warnings);
+        return result.value;
+      } catch (error) {
+        this.logError(`Error scanning UIDs: ${formatError(error)}`);
+        return null;
+      }
+    },
+    async repairDuplicateUids(dryRun = false) {
+      return this.invokeProjectEdit('repair_duplicate_uids', {}, dryRun, 'Repairing duplicate UIDs');
+    },
+    async promoteInstanceToTemplate(layoutFilePath, instanceUid, templateName) {
+      if (!this.project || !this.project.path) return null;
+      this.loading = true;
+      this.projectLoadingMessage = `Creating template '${templateName}'...`;
+      try {
+        const result = await invoke('promote_instance_to_template', {
+          payload: {
+            project_path: this.project.path,
+            layout_file_path: layoutFilePath,
+            instance_uid: instanceUid,
+            template_name: templateName,
+          },
+        });
+        this.logWarnings(result.warnings);
+        this.logLine(`[INFO] Instance ${instanceUid} is now the template '${result.value.name}' of ${result.value.object_type}.`);
+        // The command returns the new definition, no need to rescan every layout.
+        this.project.projectData.projectTemplates.push(result.value);
+        await this.fetchJournal();
+        await this.fetchLayoutInstances(layoutFilePath);
+        this.selectedInstancesForReplica.clear();
+        return result.value;
+      } catch (error) {
+        this.logError(`Error creating template '${templateName}': ${formatError(error)}`);
+        return null;
+      } finally {
+        this.loading = false;
+        this.projectLoadingMessage = '';
+      }
+    },
+    async fetchSnapshots() {
+      if (!this.project || !this.project.path) return;
+      try {
+        this.snapshots = await invoke('list_snapshots', { projectPath: this.project.path });
+      } catch (error) {
+        this.logError(`Error listing snapshots: ${formatError(error)}`);
+        this.snapshots = [];
+      }
+    },
+    async restoreSnapshot(snapshotId) {
+      if (!this.project || !this.project.path) return;
+      this.loading = true;
+      this.projectLoadingMessage = "Restoring snapshot...";
+      try {
+        const manifest = await invoke('restore_snapshot', { projectPath: this.project.path, snapshotId });
+        this.logLine(`[INFO] Restored ${manifest.files.length} files from snapshot ${manifest.id}.`);
+        this.loading = false;
+        // Restored files may be anything the command touched, reload everything.
+        await this.openPath(this.project.path, this.project.sourceC3pPath);
+        await router.push({ path: "/project/snapshots" });
+        await this.fetchSnapshots();
+      } catch (error) {
+        this.logError(`Error restoring snapshot: ${formatError(error)}`);
+      } finally {
+        this.loading = false;
+      }
+    },
+    async fetchJournal() {
+      if (!this.project || !this.project.path) return;
+      try {
+        this.journal = await invoke('get_journal', { projectPath: this.project.path });
+      } catch (error) {
+        this.logError(`Error reading undo history: ${formatError(error)}`);
+        this.journal = { operations: [], position: 0 };
+      }
+    },
+    async undoOperation() {
+      await this.stepJournal('undo_operation', 'Undid');
+    },
+    async redoOperation() {
+      await this.stepJournal('redo_operation', 'Redid');
+    },
+    async stepJournal(command, verb) {
+      if (!this.project || !this.project.path) return;
+      const currentRoute = router.currentRoute.value;
+      this.loading = true;
+      try {
+        const operation = await invoke(command, { projectPath: this.project.path });
+        this.logLine(`[INFO] ${verb} ${operation.command} (${operation.files.length} file(s)).`);
+        this.loading = false;
+        // Any file may have changed, reload everything and go back to where we were.
+        await this.openPath(this.project.path, this.project.sourceC3pPath);
+        await router.push({ path: currentRoute.path, query: currentRoute.query });
+      } catch (error) {
+        // Conflicts mean a file was edited outside the app, nothing was written.
+        this.logError(`Error: ${formatError(error)}`);
+        await this.fetchJournal();
+      } finally {
+        this.loading = false;
+      }
+    },
     toggleInstanceSelectionForReplica(instanceUid) {
         if (this.selectedInstancesForReplica.has(instanceUid)) {
             this.selectedInstancesForReplica.delete(instanceUid);
@@ -448,7 +669,7 @@ export const useAppStore = defineStore("app", {
         }
     },
     logError(error) {
-      const errorMessage = typeof error === 'string' ? error : (error.message || 'An unknown error occurred');
+      const errorMessage = formatError(error);
       console.error(error);
       this.logLine(`[ERROR]: ${errorMessage}`);
       this.projectLoadingMessage = `Error: ${errorMessage.substring(0, 200)}...`;